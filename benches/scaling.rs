@@ -0,0 +1,278 @@
+//! Measures how the analysis scales with the number of threads.
+//!
+//! Run with `cargo bench --bench scaling`. Pass a directory of plain text files to analyze it instead of generated text,
+//! such as `cargo bench --bench scaling -- ./corpus/`.
+//!
+//! Each thread count is run in a fresh process so it starts without the words interned by earlier runs,
+//! and with an empty stats cache so every file is analyzed and its words are stored in the cache, the same as a first run of the CLI.
+//!
+//! Peak memory use is only reported on Linux.
+
+use std::path::PathBuf;
+
+use japanese_text_analyzer::{
+    Analyzer, AnalyzerOptions, args_parser::AnalysisType, dict_handler, file_handler,
+    stats_cache::StatsCache,
+};
+
+//Runs a single thread count on a directory and prints its seconds and peak RSS
+const RUN_THREADS_ARG: &str = "--run-threads=";
+const GENERATED_FILE_COUNT: usize = 32;
+const GENERATED_SENTENCES_PER_FILE: usize = 20_000;
+//Nouns are made of two of these so there are thousands of them
+const GENERATED_KANJI: &str = "日本人年大中出子生時行見月分後前間事学自社者地業方新場員立開手力問代明動京目通言理体田主題意不作用度強公持野以思家世多正安院心界教文元重近考画海売知道集別物使品計死特私始朝運終台広住無真有口少町料工建空急止送切転研足究楽起着店病質待試族銀早映親験英医仕去味写字答夜音注帰古歌買悪図週室歩風紙黒花春赤青館屋色走秋夏習駅洋旅服夕借曜飲肉貸堂鳥飯勉冬昼茶弟牛魚兄犬妹姉漢";
+const GENERATED_KANA_WORDS: [&str; 24] = [
+    "こと",
+    "もの",
+    "ところ",
+    "とき",
+    "ため",
+    "よう",
+    "わたし",
+    "あなた",
+    "かれ",
+    "かのじょ",
+    "みんな",
+    "ここ",
+    "そこ",
+    "あそこ",
+    "いま",
+    "きょう",
+    "あした",
+    "ほんとう",
+    "ちょっと",
+    "すこし",
+    "たくさん",
+    "ゆっくり",
+    "しずか",
+    "きれい",
+];
+const GENERATED_KATAKANA_WORDS: [&str; 12] = [
+    "テレビ",
+    "コーヒー",
+    "ホテル",
+    "レストラン",
+    "パソコン",
+    "ニュース",
+    "ゲーム",
+    "カメラ",
+    "メール",
+    "バス",
+    "ドア",
+    "ページ",
+];
+const GENERATED_PARTICLES: [&str; 11] = [
+    "は", "が", "を", "に", "で", "と", "の", "も", "へ", "から", "まで",
+];
+const GENERATED_PREDICATES: [&str; 16] = [
+    "する",
+    "した",
+    "います",
+    "ありました",
+    "なる",
+    "言った",
+    "見ている",
+    "思います",
+    "行きたい",
+    "食べた",
+    "書いている",
+    "読みました",
+    "来る",
+    "分からない",
+    "だ",
+    "です",
+];
+const GENERATED_SENTENCE_ENDS: [&str; 4] = ["。", "。", "！", "？"];
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(some) = args.iter().find_map(|x| x.strip_prefix(RUN_THREADS_ARG)) {
+        let thread_count: usize = some.parse().expect("Invalid thread count");
+        let directory = args
+            .iter()
+            .skip(1)
+            .find(|x| !x.starts_with("--"))
+            .expect("No directory to analyze");
+        run_thread_count(thread_count, directory);
+        return;
+    }
+
+    let input_path = args.iter().skip(1).find(|x| !x.starts_with("--")).cloned();
+    let generated_dir = std::env::temp_dir().join(format!(
+        "japanese_text_analyzer_bench_{}",
+        std::process::id()
+    ));
+    let directory = match &input_path {
+        Some(some) => some.clone(),
+        None => {
+            generate_files(&generated_dir);
+            generated_dir.to_string_lossy().to_string()
+        }
+    };
+    let filepaths = file_handler::get_files(&directory, "");
+    let byte_count: u64 = filepaths
+        .iter()
+        .filter_map(|x| std::fs::metadata(x).ok())
+        .map(|x| x.len())
+        .sum();
+    println!("Analyzing {} files ({} bytes)", filepaths.len(), byte_count);
+
+    let max_threads = std::thread::available_parallelism().map_or(1, |x| x.get());
+    let mut thread_counts: Vec<usize> = std::iter::successors(Some(1), |x| Some(x * 2))
+        .take_while(|x| *x < max_threads)
+        .collect();
+    thread_counts.push(max_threads);
+
+    println!("threads\tseconds\tMB/s\tspeedup\tpeak RSS MB");
+    let mut single_thread_seconds = 0.0;
+    for thread_count in thread_counts {
+        let output = std::process::Command::new(
+            std::env::current_exe().expect("Failed to find benchmark executable"),
+        )
+        .arg(format!("{}{}", RUN_THREADS_ARG, thread_count))
+        .arg(&directory)
+        .stderr(std::process::Stdio::inherit())
+        .output()
+        .expect("Failed to run benchmark process");
+        assert!(output.status.success(), "Benchmark process failed");
+        let output = String::from_utf8_lossy(&output.stdout);
+        let (seconds, peak_rss) = output
+            .trim()
+            .split_once('\t')
+            .expect("Invalid benchmark process output");
+        let seconds: f64 = seconds.parse().expect("Invalid benchmark seconds");
+
+        if thread_count == 1 {
+            single_thread_seconds = seconds;
+        }
+        println!(
+            "{}\t{:.2}\t{:.1}\t{:.2}x\t{}",
+            thread_count,
+            seconds,
+            byte_count as f64 / 1_000_000.0 / seconds,
+            single_thread_seconds / seconds,
+            peak_rss
+        );
+    }
+
+    if input_path.is_none() {
+        let _ = std::fs::remove_dir_all(generated_dir);
+    }
+}
+
+fn run_thread_count(thread_count: usize, directory: &str) {
+    let filepaths = file_handler::get_files(directory, "");
+    let dict = dict_handler::make_sudachi_dict().expect("Failed to load tokenizer dictionary");
+    let thread_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(thread_count)
+        .build()
+        .expect("Failed to build thread pool");
+    //Kept outside the analyzed directory so its files aren't analyzed
+    let cache_dir = std::env::temp_dir().join(format!(
+        "japanese_text_analyzer_bench_cache_{}",
+        std::process::id()
+    ));
+    let analyzer = Analyzer::new(
+        &dict,
+        AnalyzerOptions {
+            analysis_type: AnalysisType::Any,
+            word_list_raw_writer: Some(Box::new(std::io::sink())),
+            stats_cache: Some(StatsCache::new(cache_dir.clone(), "bench")),
+            ..Default::default()
+        },
+    );
+
+    reset_peak_rss();
+    let start_time = std::time::Instant::now();
+    let volume_stats = thread_pool
+        .install(|| analyzer.analyze_files(&filepaths))
+        .expect("Analysis failed");
+    analyzer.finish().expect("Failed to write raw word list");
+    let seconds = start_time.elapsed().as_secs_f64();
+    std::hint::black_box(volume_stats);
+    let _ = std::fs::remove_dir_all(cache_dir);
+
+    println!(
+        "{}\t{}",
+        seconds,
+        get_peak_rss_megabytes().map_or("n/a".to_string(), |x| format!("{:.1}", x))
+    );
+}
+
+//Sentences of nouns and particles ending in a predicate, with words picked far more often the earlier they are in their list
+//so word frequencies fall off like in real text
+fn generate_files(directory: &PathBuf) {
+    std::fs::create_dir_all(directory).expect("Failed to create benchmark directory");
+    let kanji: Vec<char> = GENERATED_KANJI.chars().collect();
+    let mut random = Xorshift64 {
+        state: 0x9E3779B97F4A7C15,
+    };
+    for file_index in 0..GENERATED_FILE_COUNT {
+        let mut text = String::new();
+        for _ in 0..GENERATED_SENTENCES_PER_FILE {
+            let quoted = random.next_index(8) == 0;
+            if quoted {
+                text.push('「');
+            }
+            for _ in 0..1 + random.next_index(4) {
+                match random.next_index(4) {
+                    0 => text.push_str(*random.pick(&GENERATED_KANA_WORDS)),
+                    1 => text.push_str(*random.pick(&GENERATED_KATAKANA_WORDS)),
+                    _ => {
+                        text.push(*random.pick(&kanji));
+                        text.push(*random.pick(&kanji));
+                    }
+                }
+                text.push_str(GENERATED_PARTICLES[random.next_index(GENERATED_PARTICLES.len())]);
+            }
+            text.push_str(*random.pick(&GENERATED_PREDICATES));
+            text.push_str(
+                GENERATED_SENTENCE_ENDS[random.next_index(GENERATED_SENTENCE_ENDS.len())],
+            );
+            if quoted {
+                text.push('」');
+            }
+            text.push('\n');
+        }
+        std::fs::write(directory.join(format!("volume_{}.txt", file_index)), text)
+            .expect("Failed to write benchmark file");
+    }
+}
+
+//Fixed seed so every run generates the same text
+struct Xorshift64 {
+    state: u64,
+}
+
+impl Xorshift64 {
+    fn next_index(&mut self, length: usize) -> usize {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        return (self.state % length as u64) as usize;
+    }
+
+    //Skewed towards the start of the list
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        let uniform = self.next_index(1 << 16) as f64 / (1 << 16) as f64;
+        return &items[(uniform.powi(3) * items.len() as f64) as usize];
+    }
+}
+
+//Lets the run report its own peak instead of the peak of loading the dictionary
+fn reset_peak_rss() {
+    let _ = std::fs::write("/proc/self/clear_refs", "5");
+}
+
+fn get_peak_rss_megabytes() -> Option<f64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kilobytes: f64 = status
+        .lines()
+        .find_map(|x| x.strip_prefix("VmHWM:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    return Some(kilobytes / 1000.0);
+}
@@ -0,0 +1,21 @@
+#[path = "src/hash_handler.rs"]
+#[allow(dead_code)]
+mod hash_handler;
+
+//Hashing the embedded dictionary at build time names the decompressed dictionary cache so a build with a different dictionary never reads it
+fn main() {
+    if std::env::var_os("CARGO_FEATURE_EMBEDDED_DICTIONARY").is_none() {
+        return;
+    }
+    let dictionary_path = std::path::PathBuf::from("src/system_full.dic.zst");
+    println!(
+        "cargo:rerun-if-changed={}",
+        dictionary_path.to_string_lossy()
+    );
+    let (dictionary_hash, _) = hash_handler::hash_file(&dictionary_path)
+        .expect("Failed to read src/system_full.dic.zst. Run setup.sh or setup.bat to download it");
+    println!(
+        "cargo:rustc-env=EMBEDDED_DICTIONARY_HASH={:016x}",
+        dictionary_hash
+    );
+}
@@ -1,7 +1,20 @@
+use serde::Serialize;
 use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Write};
 use std::hash::Hash;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 
+use crate::interner::Word;
+
+const SENTENCE_TERMINATORS: [char; 6] = ['。', '！', '？', '!', '?', '｡'];
+const OPENING_BRACKETS: [char; 9] = ['「', '『', '（', '(', '【', '〈', '《', '“', '｢'];
+const CLOSING_BRACKETS: [char; 9] = ['」', '』', '）', ')', '】', '〉', '》', '”', '｣'];
+//Particles which continue a sentence after a closing bracket such as `「はい」と言った`
+const BRACKET_CONTINUATIONS: [char; 12] = [
+    'と', 'っ', 'を', 'に', 'の', 'は', 'が', 'で', 'だ', 'も', 'へ', 'や',
+];
+
 pub fn count_directories(filepaths: &Vec<PathBuf>) -> usize {
     return filepaths
         .iter()
@@ -28,48 +41,207 @@ pub fn generate_occurrence_list<T: ToOwned<Owned = T> + Eq + Hash>(
         });
 }
 
-pub fn sort_occurrence_list<T: ToString>(occurrence_list: HashMap<T, u64>) -> Vec<(String, u64)> {
-    let mut occurrence_list_sorted: Vec<(String, u64)> = occurrence_list
-        .iter()
-        .map(|x| (x.0.to_string(), x.1.to_owned()))
-        .collect();
+pub fn sort_occurrence_list<T>(occurrence_list: &HashMap<T, u64>) -> Vec<(&T, u64)> {
+    let mut occurrence_list_sorted: Vec<(&T, u64)> =
+        occurrence_list.iter().map(|x| (x.0, *x.1)).collect();
     occurrence_list_sorted.sort_by(|a, b| b.1.cmp(&a.1));
     return occurrence_list_sorted;
 }
 
-pub fn find_single_occurrences<T: ToOwned<Owned = T> + Eq + Hash>(
-    occurrence_list: &HashMap<T, u64>,
-) -> HashSet<T> {
-    return occurrence_list
+/// Formats an occurrence list as `item\tcount` lines sorted by count.
+pub fn format_occurrence_list<T: Display>(occurrence_list: &HashMap<T, u64>) -> String {
+    let mut occurrence_list_formatted = String::new();
+    for (item, count) in sort_occurrence_list(occurrence_list) {
+        if !occurrence_list_formatted.is_empty() {
+            occurrence_list_formatted.push('\n');
+        }
+        let _ = write!(occurrence_list_formatted, "{}\t{}", item, count);
+    }
+    return occurrence_list_formatted;
+}
+
+pub fn count_single_occurrences<T>(occurrence_list: &HashMap<T, u64>) -> usize {
+    return occurrence_list.values().filter(|x| **x == 1).count();
+}
+
+/// Gets the length in characters of each line containing Japanese.
+pub fn get_line_lengths(lines: &Vec<String>) -> Vec<usize> {
+    return lines
         .iter()
-        .filter(|x| *x.1 == 1)
-        .map(|x| x.0.to_owned())
+        .filter(|x| !check_if_blacklisted(x))
+        .map(|x| x.chars().count())
+        .collect();
+}
+
+/// Splits a line into sentences and returns their character ranges.
+///
+/// Sentences end at `。！？` along with any terminators or closing brackets directly following them. Terminators inside brackets do not end a sentence.
+/// A closing bracket ends a sentence when the sentence started with the bracket and the bracket is not followed by a particle continuing the sentence.
+/// Ellipses (`…` `‥`) never end a sentence on their own.
+pub fn split_sentences(line: &str) -> Vec<Range<usize>> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut sentence_ranges: Vec<Range<usize>> = vec![];
+    let mut sentence_start = 0;
+    let mut bracket_depth: usize = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let char = chars[i];
+        let mut sentence_end = false;
+        if OPENING_BRACKETS.contains(&char) {
+            bracket_depth += 1;
+        } else if CLOSING_BRACKETS.contains(&char) {
+            bracket_depth = bracket_depth.saturating_sub(1);
+            sentence_end = bracket_depth == 0
+                && chars[sentence_start..i]
+                    .iter()
+                    .find(|x| !x.is_whitespace())
+                    .is_some_and(|x| OPENING_BRACKETS.contains(x))
+                && chars
+                    .get(i + 1)
+                    .is_none_or(|x| !BRACKET_CONTINUATIONS.contains(x));
+        } else if bracket_depth == 0 && SENTENCE_TERMINATORS.contains(&char) {
+            sentence_end = true;
+        }
+
+        if sentence_end {
+            while let Some(next_char) = chars.get(i + 1) {
+                if SENTENCE_TERMINATORS.contains(next_char) {
+                    i += 1;
+                } else if CLOSING_BRACKETS.contains(next_char) {
+                    bracket_depth = bracket_depth.saturating_sub(1);
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            push_trimmed_range(&mut sentence_ranges, &chars, sentence_start..i + 1);
+            sentence_start = i + 1;
+        }
+        i += 1;
+    }
+    push_trimmed_range(&mut sentence_ranges, &chars, sentence_start..chars.len());
+    return sentence_ranges;
+}
+
+fn push_trimmed_range(ranges: &mut Vec<Range<usize>>, chars: &Vec<char>, range: Range<usize>) {
+    let mut range = range;
+    while range.start < range.end && chars[range.start].is_whitespace() {
+        range.start += 1;
+    }
+    while range.start < range.end && chars[range.end - 1].is_whitespace() {
+        range.end -= 1;
+    }
+    if range.start < range.end {
+        ranges.push(range);
+    }
+}
+
+/// Gets the length in characters and in words of each sentence in a line containing Japanese.
+///
+/// `morpheme_surfaces` must be the tokenized `line`. Only words containing Japanese are counted, the same as for the total word count.
+pub fn get_sentence_lengths(line: &str, morpheme_surfaces: &Vec<Word>) -> Vec<(usize, usize)> {
+    let line_chars: Vec<char> = line.chars().collect();
+    let sentence_ranges: Vec<Range<usize>> = split_sentences(line)
+        .into_iter()
+        .filter(|x| filter_non_japanese(line_chars[x.clone()].to_vec()).len() > 0)
         .collect();
+    let mut sentence_lengths: Vec<(usize, usize)> =
+        sentence_ranges.iter().map(|x| (x.len(), 0)).collect();
+    if sentence_lengths.is_empty() {
+        return sentence_lengths;
+    }
+
+    let mut sentence_index = 0;
+    let mut morpheme_start = 0;
+    for morpheme_surface in morpheme_surfaces {
+        while sentence_index + 1 < sentence_ranges.len()
+            && morpheme_start >= sentence_ranges[sentence_index].end
+        {
+            sentence_index += 1;
+        }
+        if !check_if_blacklisted(morpheme_surface) {
+            sentence_lengths[sentence_index].1 += 1;
+        }
+        morpheme_start += morpheme_surface.chars().count();
+    }
+    return sentence_lengths;
+}
+
+/// Summarizes a list of lengths along with how many times each length occurred.
+pub fn summarize_lengths(length_occurrence_list: &HashMap<usize, u64>) -> LengthSummary {
+    let mut sorted_lengths: Vec<(&usize, &u64)> = length_occurrence_list.iter().collect();
+    sorted_lengths.sort();
+    let count: u64 = sorted_lengths.iter().map(|x| x.1).sum();
+    let length_sum: u64 = sorted_lengths.iter().map(|x| *x.0 as u64 * x.1).sum();
+    return LengthSummary {
+        count: count,
+        average: f64::max(length_sum as f64 / count as f64, 0.0), // override NaN with 0.0
+        median: get_percentile(&sorted_lengths, count, 50.0),
+        percentile_90: get_percentile(&sorted_lengths, count, 90.0),
+        percentile_99: get_percentile(&sorted_lengths, count, 99.0),
+        shortest: sorted_lengths.first().map(|x| *x.0).unwrap_or_default(),
+        longest: sorted_lengths.last().map(|x| *x.0).unwrap_or_default(),
+    };
 }
 
-pub fn get_avg_len(lines: Vec<String>) -> Option<BoxLength> {
-    let parent_len = lines.len();
-    let mut lens = Vec::default();
-    let lines_length = lines.len();
-    let filtered_lines = filter_blacklisted(lines);
-    for line in &filtered_lines {
-        lens.push(line.chars().count())
+//nearest-rank percentile
+fn get_percentile(sorted_lengths: &Vec<(&usize, &u64)>, count: u64, percentile: f64) -> usize {
+    let rank = u64::max((percentile / 100.0 * count as f64).ceil() as u64, 1);
+    let mut seen_count = 0;
+    for (length, occurrences) in sorted_lengths {
+        seen_count += *occurrences;
+        if seen_count >= rank {
+            return **length;
+        }
     }
-    lens.sort();
-    return Some(BoxLength {
-        average: usize::checked_div(lens.iter().sum::<usize>(), parent_len).unwrap_or_default(),
-        shortest: *lens.first()?,
-        longest: *lens.last()?,
-        length: lines_length,
-    });
+    return 0;
 }
 
-#[derive(Default, Debug)]
-pub struct BoxLength {
-    pub average: usize,
+#[derive(Default, Debug, Serialize)]
+pub struct LengthSummary {
+    pub count: u64,
+    pub average: f64,
+    pub median: usize,
+    pub percentile_90: usize,
+    pub percentile_99: usize,
     pub shortest: usize,
     pub longest: usize,
-    pub length: usize,
+}
+
+/// Gets the area of a polygon from its `[x, y]` points with the shoelace formula.
+pub fn get_polygon_area(points: &Vec<Vec<f64>>) -> f64 {
+    let mut doubled_area = 0.0;
+    for (i, point) in points.iter().enumerate() {
+        let next_point = &points[(i + 1) % points.len()];
+        if let ([x1, y1, ..], [x2, y2, ..]) = (point.as_slice(), next_point.as_slice()) {
+            doubled_area += x1 * y2 - x2 * y1;
+        }
+    }
+    return f64::abs(doubled_area) / 2.0;
+}
+
+/// Formats length occurrence lists as tab separated columns with a header. Every length occurring in at least one of the lists gets a row.
+pub fn format_length_histogram(
+    length_name: &str,
+    columns: Vec<(&str, &HashMap<usize, u64>)>,
+) -> String {
+    let mut lengths: Vec<usize> = columns.iter().flat_map(|x| x.1.keys()).copied().collect();
+    lengths.sort();
+    lengths.dedup();
+    let mut rows: Vec<String> = vec![
+        std::iter::once(length_name)
+            .chain(columns.iter().map(|x| x.0))
+            .collect::<Vec<&str>>()
+            .join("\t"),
+    ];
+    for length in lengths {
+        let mut row = vec![length.to_string()];
+        for column in &columns {
+            row.push(column.1.get(&length).unwrap_or(&0).to_string());
+        }
+        rows.push(row.join("\t"));
+    }
+    return rows.join("\n");
 }
 
 pub fn filter_duplicate_ascii(input_string: String) -> Vec<String> {
@@ -119,10 +291,15 @@ where
 pub fn filter_blacklisted(words: Vec<String>) -> Vec<String> {
     return words
         .into_iter()
-        .filter(|x| filter_non_japanese(x.chars().collect()).len() > 0)
+        .filter(|x| !check_if_blacklisted(x))
         .collect();
 }
 
+/// Whether a word is left out of the word counts because it contains no Japanese.
+pub fn check_if_blacklisted(word: &str) -> bool {
+    return !word.chars().any(|x| check_if_japanese(x as u32));
+}
+
 fn check_if_ascii(codepoint: u32) -> bool {
     //ascii goes all the way to 0x0000 and codepoints cannot be negative
     if codepoint <= 0x007F {
@@ -131,28 +308,131 @@ fn check_if_ascii(codepoint: u32) -> bool {
     return false;
 }
 
-fn check_if_japanese(codepoint: u32) -> bool {
+pub fn check_if_japanese(codepoint: u32) -> bool {
+    return get_char_class(codepoint).is_some_and(|x| x.is_japanese());
+}
+
+pub fn check_if_kana(codepoint: u32) -> bool {
+    return get_char_class(codepoint) == Some(CharClass::Hiragana) || check_if_katakana(codepoint);
+}
+
+pub fn check_if_katakana(codepoint: u32) -> bool {
+    return matches!(
+        get_char_class(codepoint),
+        Some(CharClass::Katakana | CharClass::HalfWidthKatakana)
+    );
+}
+
+/// Classes characters are counted by. Hiragana, katakana, half-width katakana, kanji, and extended kana make up the Japanese characters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CharClass {
+    Hiragana,
+    Katakana,
+    HalfWidthKatakana,
+    Kanji,
+    /// Hentaigana and small kana outside of the hiragana and katakana blocks.
+    ExtendedKana,
+    /// Japanese punctuation and symbols, kana sound and iteration marks, and full-width punctuation.
+    JapanesePunctuation,
+    FullWidthAlphanumeric,
+    /// Latin letters and digits, including accented letters.
+    Latin,
+}
+
+impl CharClass {
+    /// Every class in the order they are reported.
+    pub const ALL: [CharClass; 8] = [
+        CharClass::Hiragana,
+        CharClass::Katakana,
+        CharClass::HalfWidthKatakana,
+        CharClass::Kanji,
+        CharClass::ExtendedKana,
+        CharClass::JapanesePunctuation,
+        CharClass::FullWidthAlphanumeric,
+        CharClass::Latin,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            CharClass::Hiragana => return "hiragana",
+            CharClass::Katakana => return "katakana",
+            CharClass::HalfWidthKatakana => return "half_width_katakana",
+            CharClass::Kanji => return "kanji",
+            CharClass::ExtendedKana => return "extended_kana",
+            CharClass::JapanesePunctuation => return "japanese_punctuation",
+            CharClass::FullWidthAlphanumeric => return "full_width_alphanumeric",
+            CharClass::Latin => return "latin",
+        }
+    }
+
+    pub fn is_japanese(&self) -> bool {
+        return matches!(
+            self,
+            CharClass::Hiragana
+                | CharClass::Katakana
+                | CharClass::HalfWidthKatakana
+                | CharClass::Kanji
+                | CharClass::ExtendedKana
+        );
+    }
+}
+
+/// Gets the class of a character. Characters such as spaces and symbols outside of Japanese text have no class.
+pub fn get_char_class(codepoint: u32) -> Option<CharClass> {
     //Kanji
-    if check_if_kanji(codepoint) ||
+    if check_if_kanji(codepoint) {
+        return Some(CharClass::Kanji);
+    }
     //Hiragana (punctuation excluded: U+3099..U+309E; full range: U+3040..U+309F)
-    codepoint >= 0x3040 && codepoint <= 0x3096 || codepoint == 0x309F ||
+    if codepoint >= 0x3040 && codepoint <= 0x3096 || codepoint == 0x309F {
+        return Some(CharClass::Hiragana);
+    }
     //Katakana (punctuation excluded U+30A0, U+30FB..U+30FF; full range: U+30A0..U+30FF)
-    codepoint >= 0x30A1 && codepoint <= 0x30FA ||
+    if codepoint >= 0x30A1 && codepoint <= 0x30FA {
+        return Some(CharClass::Katakana);
+    }
     //Half-width Katakana (non-japanese excluded: U+FF01..U+FF63, U+FFA0..U+FFEF; japanese sound marks excluded: U+FF9E..U+FF9F; japanese punctuation excluded: U+FF64..U+FF65; full range: U+FF00..U+FFEF)
-    codepoint >= 0xFF66 && codepoint <= 0xFF9D ||
+    if codepoint >= 0xFF66 && codepoint <= 0xFF9D {
+        return Some(CharClass::HalfWidthKatakana);
+    }
     //Small Kana Extension
-    codepoint >= 0x1B130 && codepoint <= 0x1B16F ||
+    if codepoint >= 0x1B130 && codepoint <= 0x1B16F ||
     //Kana Extended A (Hentaigana and reserved small kana punctuation) (Reserved punctuation excluded: U+1B12B..U+1B12F; full range: U+1B100..U+1B12F)
     codepoint >= 0x1B100 && codepoint <= 0x1B122 ||
     //Kana Supplement (Hentaigana)
     codepoint >= 0x1B000 && codepoint <= 0x1B0FF
     {
-        return true;
+        return Some(CharClass::ExtendedKana);
     }
-    return false;
+    //Full-width digits, uppercase letters, and lowercase letters
+    if codepoint >= 0xFF10 && codepoint <= 0xFF19
+        || codepoint >= 0xFF21 && codepoint <= 0xFF3A
+        || codepoint >= 0xFF41 && codepoint <= 0xFF5A
+    {
+        return Some(CharClass::FullWidthAlphanumeric);
+    }
+    //CJK Symbols and Punctuation (ideographic space excluded: U+3000; full range: U+3000..U+303F)
+    if codepoint >= 0x3001 && codepoint <= 0x303F ||
+    //Kana sound and iteration marks and katakana punctuation excluded from hiragana and katakana
+    codepoint >= 0x3099 && codepoint <= 0x309E || codepoint == 0x30A0 || codepoint >= 0x30FB && codepoint <= 0x30FF ||
+    //Full-width punctuation and half-width Japanese punctuation (full-width alphanumerics excluded; full range: U+FF01..U+FF65)
+    codepoint >= 0xFF01 && codepoint <= 0xFF65 ||
+    //Half-width sound marks
+    codepoint >= 0xFF9E && codepoint <= 0xFF9F
+    {
+        return Some(CharClass::JapanesePunctuation);
+    }
+    //Basic Latin digits and letters
+    if codepoint >= 0x30 && codepoint <= 0x39 || codepoint >= 0x41 && codepoint <= 0x5A || codepoint >= 0x61 && codepoint <= 0x7A ||
+    //Latin-1 Supplement, Latin Extended-A, and Latin Extended-B letters (`×` and `÷` excluded: U+D7, U+F7)
+    codepoint >= 0xC0 && codepoint <= 0x24F && codepoint != 0xD7 && codepoint != 0xF7
+    {
+        return Some(CharClass::Latin);
+    }
+    return None;
 }
 
-fn check_if_kanji(codepoint: u32) -> bool {
+pub fn check_if_kanji(codepoint: u32) -> bool {
     //CJK Unified Ideographs
     if codepoint >= 0x4E00 && codepoint <= 0x9FFF ||
     //CJK Unified Ideographs Extension A
@@ -183,17 +463,15 @@ pub fn get_fancy_percentage(base: f64, percent: f64) -> String {
     return format!("{:.2}%", percent / base * 100.0);
 }
 
-pub fn bounded_min<T: PartialEq + PartialOrd + Ord>(val1: T, val2: T, min: T) -> T {
-    if val1 < val2 {
-        return T::max(val1, min);
-    }
-    return T::max(val2, min);
-}
-
 pub fn merge_hashmap<T: Eq + Hash>(
-    mut hashmap1: HashMap<T, u64>,
+    hashmap1: HashMap<T, u64>,
     hashmap2: HashMap<T, u64>,
 ) -> HashMap<T, u64> {
+    //Adding the smaller map into the larger one keeps combining large stats cheap
+    let (mut hashmap1, hashmap2) = match hashmap1.len() >= hashmap2.len() {
+        true => (hashmap1, hashmap2),
+        false => (hashmap2, hashmap1),
+    };
     for (k, v) in hashmap2 {
         let _ = *hashmap1.entry(k).and_modify(|x| *x += v).or_insert(v);
     }
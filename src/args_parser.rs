@@ -22,6 +22,53 @@ pub fn get_args(args: Vec<String>) -> JapaneseTextAnalyzerArgs {
                 "--singlethreaded" => {
                     japanese_text_analyzer_args.singlethreaded = true;
                 }
+                "--threads" => {
+                    japanese_text_analyzer_args.threads = Some(split_arg.1.to_string());
+                }
+                "--chunk-size" => {
+                    japanese_text_analyzer_args.chunk_size = Some(split_arg.1.to_string());
+                }
+                "--frequency-list" => {
+                    japanese_text_analyzer_args.frequency_list = Some(split_arg.1.to_string());
+                }
+                "--noise-filter" => {
+                    japanese_text_analyzer_args.noise_filter = Some(split_arg.1.to_string());
+                }
+                "--dedupe-lines" => {
+                    japanese_text_analyzer_args.dedupe_lines = true;
+                }
+                "--quiet" => {
+                    japanese_text_analyzer_args.quiet = true;
+                }
+                "--normalize" => {
+                    japanese_text_analyzer_args.normalize = Some(split_arg.1.to_string());
+                }
+                "--lossy-utf8" => {
+                    japanese_text_analyzer_args.lossy_utf8 = true;
+                }
+                "--strict" => {
+                    japanese_text_analyzer_args.strict = true;
+                }
+                "--dedupe-files" => {
+                    japanese_text_analyzer_args.dedupe_files = true;
+                }
+                "--no-cache" => {
+                    japanese_text_analyzer_args.no_cache = true;
+                }
+                "--dict" => {
+                    japanese_text_analyzer_args.dict = Some(split_arg.1.to_string());
+                }
+                "--user-dict" => {
+                    japanese_text_analyzer_args
+                        .user_dicts
+                        .push(split_arg.1.to_string());
+                }
+                "--sudachi-config" => {
+                    japanese_text_analyzer_args.sudachi_config = Some(split_arg.1.to_string());
+                }
+                "--difficulty-weights" => {
+                    japanese_text_analyzer_args.difficulty_weights = Some(split_arg.1.to_string());
+                }
                 "-h" | "--help" => {
                     japanese_text_analyzer_args.help = true;
                 }
@@ -34,12 +81,40 @@ pub fn get_args(args: Vec<String>) -> JapaneseTextAnalyzerArgs {
     return japanese_text_analyzer_args;
 }
 
+/// Parses the value of an option which has to be a whole number above 0.
+pub fn parse_positive_integer(option: &str, value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(ok) if ok > 0 => return Ok(ok),
+        _ => {
+            return Err(format!(
+                "Invalid value `{}` for `{}`. Expected a whole number above 0",
+                value, option
+            ));
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct JapaneseTextAnalyzerArgs {
     pub start_path: String,
     pub analysis_type: AnalysisType,
     pub extension: String,
     pub singlethreaded: bool,
+    pub threads: Option<String>,
+    pub chunk_size: Option<String>,
+    pub frequency_list: Option<String>,
+    pub difficulty_weights: Option<String>,
+    pub noise_filter: Option<String>,
+    pub dedupe_lines: bool,
+    pub dedupe_files: bool,
+    pub strict: bool,
+    pub lossy_utf8: bool,
+    pub normalize: Option<String>,
+    pub quiet: bool,
+    pub no_cache: bool,
+    pub dict: Option<String>,
+    pub user_dicts: Vec<String>,
+    pub sudachi_config: Option<String>,
     pub help: bool,
 }
 
@@ -50,13 +125,29 @@ impl JapaneseTextAnalyzerArgs {
             analysis_type: AnalysisType::MokuroJson,
             extension: ".json".to_string(),
             singlethreaded: false,
+            threads: None,
+            chunk_size: None,
+            frequency_list: None,
+            difficulty_weights: None,
+            noise_filter: None,
+            dedupe_lines: false,
+            dedupe_files: false,
+            strict: false,
+            lossy_utf8: false,
+            normalize: None,
+            quiet: false,
+            no_cache: false,
+            dict: None,
+            user_dicts: vec![],
+            sudachi_config: None,
             help: false,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Default)]
 pub enum AnalysisType {
+    #[default]
     MokuroJson,
     Mokuro,
     Any,
@@ -0,0 +1,46 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+const CACHE_DIR_NAME: &str = "japanese_text_analyzer";
+const CACHE_DIR_ENV_VAR: &str = "JAPANESE_TEXT_ANALYZER_CACHE_DIR";
+
+/// Directory for data kept between runs. `JAPANESE_TEXT_ANALYZER_CACHE_DIR` overrides the default of
+/// `$XDG_CACHE_HOME/japanese_text_analyzer`, `~/.cache/japanese_text_analyzer`, or `%LOCALAPPDATA%\japanese_text_analyzer`.
+pub fn get_cache_dir() -> Option<PathBuf> {
+    let non_empty_var = |name: &str| std::env::var_os(name).filter(|x| !x.is_empty());
+    if let Some(some) = non_empty_var(CACHE_DIR_ENV_VAR) {
+        return Some(PathBuf::from(some));
+    }
+    if let Some(some) = non_empty_var("XDG_CACHE_HOME") {
+        return Some(PathBuf::from(some).join(CACHE_DIR_NAME));
+    }
+    if let Some(some) = non_empty_var("LOCALAPPDATA") {
+        return Some(PathBuf::from(some).join(CACHE_DIR_NAME));
+    }
+    if let Some(some) = non_empty_var("HOME") {
+        return Some(PathBuf::from(some).join(".cache").join(CACHE_DIR_NAME));
+    }
+    return None;
+}
+
+/// Writes a file through a temporary file in the same directory so other processes never see it partially written.
+pub fn write_atomically(filepath: &Path, contents: &[u8]) -> Result<(), std::io::Error> {
+    if let Some(parent) = filepath.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut temp_filepath = filepath.as_os_str().to_owned();
+    temp_filepath.push(format!(".{}.tmp", std::process::id()));
+    let temp_filepath = PathBuf::from(temp_filepath);
+
+    let write_result = std::fs::File::create(&temp_filepath).and_then(|mut file| {
+        file.write_all(contents)?;
+        return file.sync_all();
+    });
+    let rename_result = write_result.and_then(|_| std::fs::rename(&temp_filepath, filepath));
+    if rename_result.is_err() {
+        let _ = std::fs::remove_file(&temp_filepath);
+    }
+    return rename_result;
+}
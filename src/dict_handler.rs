@@ -1,15 +1,172 @@
-use sudachi::dic::{
-    dictionary::JapaneseDictionary,
-    storage::{Storage, SudachiDicData},
+use std::path::PathBuf;
+
+use sudachi::{
+    config::Config,
+    dic::{
+        dictionary::JapaneseDictionary,
+        storage::{Storage, SudachiDicData},
+    },
 };
 
-pub fn make_sudachi_dict() -> Result<JapaneseDictionary, Box<dyn std::error::Error>> {
-    let embedded_dictionary = decode_zstd(include_bytes!("./system_full.dic.zst"))?;
-    let dictionary_file_data = SudachiDicData::new(Storage::Owned(embedded_dictionary));
-    let config = sudachi::config::Config::new_embedded()?;
-    let dictionary =
-        JapaneseDictionary::from_cfg_storage_with_embedded_chardef(&config, dictionary_file_data)?;
-    return Ok(dictionary);
+#[cfg(feature = "embedded-dictionary")]
+use crate::embedded_dict;
+use crate::{error::AnalyzerError, hash_handler};
+
+/// Dictionaries and Sudachi config to load instead of the defaults.
+#[derive(Debug, Clone, Default)]
+pub struct DictionaryOptions {
+    /// Sudachi system dictionary such as `system_small.dic`, `system_core.dic`, or `system_full.dic`. May be zstd compressed (`.zst`).
+    /// Defaults to the embedded dictionary.
+    pub system_dict: Option<PathBuf>,
+    /// Sudachi user dictionaries. May be zstd compressed (`.zst`).
+    pub user_dicts: Vec<PathBuf>,
+    /// Sudachi config json. Its `characterDefinitionFile` is used instead of the embedded one.
+    /// Its `systemDict` and `userDict` are only used when `system_dict` is not set and there is no embedded dictionary.
+    pub sudachi_config: Option<PathBuf>,
+}
+
+/// Loads the embedded dictionary.
+pub fn make_sudachi_dict() -> Result<JapaneseDictionary, AnalyzerError> {
+    return make_custom_sudachi_dict(&DictionaryOptions::default());
+}
+
+/// Loads the dictionaries and config in `options`, falling back to the embedded dictionary and config.
+pub fn make_custom_sudachi_dict(
+    options: &DictionaryOptions,
+) -> Result<JapaneseDictionary, AnalyzerError> {
+    let config = match &options.sudachi_config {
+        Some(some) => Config::from_file(some).map_err(|err| {
+            AnalyzerError::InvalidOption(format!(
+                "Failed to load Sudachi config `{}`: {}",
+                some.to_string_lossy(),
+                err
+            ))
+        })?,
+        None => Config::new_embedded().map_err(|err| AnalyzerError::Dictionary(err.to_string()))?,
+    };
+
+    let system_dictionary = match &options.system_dict {
+        Some(some) => Some(load_dictionary_file(some)?),
+        None => load_embedded_dictionary()?,
+    };
+    let Some(system_dictionary) = system_dictionary else {
+        if options.sudachi_config.is_none() {
+            return Err(AnalyzerError::Dictionary(
+                "No system dictionary was given and this build has no embedded dictionary"
+                    .to_string(),
+            ));
+        }
+        //Only the Sudachi config is left to point to a system dictionary
+        let config = options
+            .user_dicts
+            .iter()
+            .fold(config, |config, x| config.with_user_dic(x));
+        return JapaneseDictionary::from_cfg(&config)
+            .map_err(|err| AnalyzerError::Dictionary(err.to_string()));
+    };
+
+    let mut dictionary_file_data = SudachiDicData::new(system_dictionary);
+    for user_dict in &options.user_dicts {
+        dictionary_file_data.add_user(load_dictionary_file(user_dict)?);
+    }
+    let dictionary = match options.sudachi_config {
+        Some(_) => JapaneseDictionary::from_cfg_storage(&config, dictionary_file_data),
+        None => JapaneseDictionary::from_cfg_storage_with_embedded_chardef(
+            &config,
+            dictionary_file_data,
+        ),
+    };
+    return dictionary.map_err(|err| AnalyzerError::Dictionary(err.to_string()));
+}
+
+/// Identifies the dictionaries and config `make_custom_sudachi_dict` loads for `StatsCache`.
+///
+/// Dictionary files are identified by their path, size, and modification time instead of their contents so large dictionaries don't have to be read.
+/// Without `system_dict` or an embedded dictionary these are the dictionaries the Sudachi config resolves to.
+pub fn get_dictionary_version(options: &DictionaryOptions) -> String {
+    let describe_file = |filepath: &PathBuf| {
+        let metadata = std::fs::metadata(filepath).ok();
+        return format!(
+            "{}:{:?}:{:?}",
+            std::fs::canonicalize(filepath)
+                .unwrap_or_else(|_| filepath.clone())
+                .to_string_lossy(),
+            metadata.as_ref().map(|x| x.len()),
+            metadata.and_then(|x| x.modified().ok())
+        );
+    };
+    let mut version_parts: Vec<String> = vec![];
+    match (&options.system_dict, get_embedded_dictionary_version()) {
+        (Some(some), _) => version_parts.push(describe_file(some)),
+        (None, Some(some)) => version_parts.push(some),
+        (None, None) => version_parts.extend(
+            get_config_dictionary_paths(options)
+                .iter()
+                .map(describe_file),
+        ),
+    }
+    version_parts.extend(options.user_dicts.iter().map(describe_file));
+    version_parts.extend(options.sudachi_config.iter().map(describe_file));
+    return format!(
+        "{:016x}",
+        hash_handler::hash_bytes(version_parts.join("\n").as_bytes())
+    );
+}
+
+//System and user dictionaries of the Sudachi config, resolved the same way as when they are loaded
+fn get_config_dictionary_paths(options: &DictionaryOptions) -> Vec<PathBuf> {
+    let Some(config) = options
+        .sudachi_config
+        .as_ref()
+        .and_then(|x| Config::from_file(x).ok())
+    else {
+        return vec![];
+    };
+    let mut dictionary_paths: Vec<PathBuf> = config.resolved_system_dict().into_iter().collect();
+    dictionary_paths.extend(config.resolved_user_dicts().unwrap_or_default());
+    return dictionary_paths;
+}
+
+#[cfg(feature = "embedded-dictionary")]
+fn get_embedded_dictionary_version() -> Option<String> {
+    return Some(format!(
+        "embedded:{}",
+        embedded_dict::EMBEDDED_DICTIONARY_HASH
+    ));
+}
+
+#[cfg(not(feature = "embedded-dictionary"))]
+fn get_embedded_dictionary_version() -> Option<String> {
+    return None;
+}
+
+#[cfg(feature = "embedded-dictionary")]
+fn load_embedded_dictionary() -> Result<Option<Storage>, AnalyzerError> {
+    return Ok(Some(embedded_dict::load_embedded_dictionary()?));
+}
+
+#[cfg(not(feature = "embedded-dictionary"))]
+fn load_embedded_dictionary() -> Result<Option<Storage>, AnalyzerError> {
+    return Ok(None);
+}
+
+//Compressed dictionaries are decompressed into memory, others are memory-mapped
+fn load_dictionary_file(filepath: &PathBuf) -> Result<Storage, AnalyzerError> {
+    let io_error = |err: std::io::Error| {
+        AnalyzerError::InvalidOption(format!(
+            "Failed to load dictionary `{}`: {}",
+            filepath.to_string_lossy(),
+            err
+        ))
+    };
+    if filepath.extension().is_some_and(|x| x == "zst") {
+        let compressed = std::fs::read(filepath).map_err(io_error)?;
+        return Ok(Storage::Owned(decode_zstd(&compressed)?));
+    }
+    let file = std::fs::File::open(filepath).map_err(io_error)?;
+    //Safety: the dictionary files are expected not to change while the analyzer runs, the same as when Sudachi loads them itself
+    let mmap = unsafe { memmap2::Mmap::map(&file) }.map_err(io_error)?;
+    return Ok(Storage::File(mmap));
 }
 
 pub fn get_mode() -> sudachi::analysis::Mode {
@@ -38,9 +195,17 @@ pub fn get_mode() -> sudachi::analysis::Mode {
     return sudachi::analysis::Mode::B;
 }
 
-fn decode_zstd(data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let bound = zstd_safe::decompress_bound(&data).expect("zstd_safe::decompress_bound failed");
-    let mut decompressed: Vec<u8> = Vec::with_capacity(bound.try_into()?);
-    zstd_safe::decompress(&mut decompressed, &data).expect("zstd_safe::decompress failed");
+pub(crate) fn decode_zstd(data: &[u8]) -> Result<Vec<u8>, AnalyzerError> {
+    let zstd_error = |code: zstd_safe::ErrorCode| {
+        AnalyzerError::Dictionary(format!(
+            "Failed to decompress: {}",
+            zstd_safe::get_error_name(code)
+        ))
+    };
+    let bound = zstd_safe::decompress_bound(&data).map_err(zstd_error)?;
+    let mut decompressed: Vec<u8> = Vec::with_capacity(bound.try_into().map_err(|_| {
+        AnalyzerError::Dictionary("Dictionary is too large to decompress".to_string())
+    })?);
+    zstd_safe::decompress(&mut decompressed, &data).map_err(zstd_error)?;
     return Ok(decompressed);
 }
@@ -0,0 +1,116 @@
+use serde::Serialize;
+
+//Values at or above these count as maximum difficulty for their metric
+const TEXTBOX_LENGTH_CEILING: f64 = 40.0;
+const KANJI_RATIO_CEILING: f64 = 0.5;
+
+/// Relative weights of each metric in the difficulty score. Metrics with a weight of 0 are ignored.
+#[derive(Debug, Clone, Serialize)]
+pub struct DifficultyWeights {
+    pub textbox_length: f64,
+    pub kanji_ratio: f64,
+    pub unique_word_ratio: f64,
+    pub single_occurrence_ratio: f64,
+    pub vocabulary_rarity: f64,
+}
+
+impl Default for DifficultyWeights {
+    fn default() -> Self {
+        Self {
+            textbox_length: 1.0,
+            kanji_ratio: 1.0,
+            unique_word_ratio: 1.0,
+            single_occurrence_ratio: 1.0,
+            vocabulary_rarity: 1.0,
+        }
+    }
+}
+
+/// Parses weights in the format `textbox:1,kanji:1,unique:1,single:1,rarity:1`. Metrics which are not specified keep their default weight.
+pub fn parse_difficulty_weights(weights_string: &str) -> Result<DifficultyWeights, String> {
+    let mut weights = DifficultyWeights::default();
+    for weight_string in weights_string.split(',').filter(|x| !x.is_empty()) {
+        let (name, value) = weight_string
+            .split_once(':')
+            .ok_or_else(|| format!("Missing `:` in difficulty weight `{}`", weight_string))?;
+        let value = value
+            .parse::<f64>()
+            .ok()
+            .filter(|x| x.is_finite() && *x >= 0.0)
+            .ok_or_else(|| format!("Invalid difficulty weight value `{}`", value))?;
+        match name {
+            "textbox" => weights.textbox_length = value,
+            "kanji" => weights.kanji_ratio = value,
+            "unique" => weights.unique_word_ratio = value,
+            "single" => weights.single_occurrence_ratio = value,
+            "rarity" => weights.vocabulary_rarity = value,
+            _ => return Err(format!("Unknown difficulty weight `{}`", name)),
+        }
+    }
+    return Ok(weights);
+}
+
+#[derive(Debug, Default)]
+pub struct DifficultyMetrics {
+    pub avg_box_length: f64,
+    pub kanji_ratio: f64,
+    pub unique_word_ratio: f64,
+    pub single_occurrence_ratio: f64,
+    /// Only available when a reference frequency list was supplied.
+    pub vocabulary_rarity: Option<f64>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DifficultyScore {
+    /// Weighted average of all available metrics scaled to 0-100.
+    pub score: f64,
+    pub textbox_length: f64,
+    pub kanji_ratio: f64,
+    pub unique_word_ratio: f64,
+    pub single_occurrence_ratio: f64,
+    pub vocabulary_rarity: Option<f64>,
+}
+
+pub fn get_difficulty_score(
+    metrics: DifficultyMetrics,
+    weights: &DifficultyWeights,
+) -> DifficultyScore {
+    let textbox_length = normalize(metrics.avg_box_length, TEXTBOX_LENGTH_CEILING);
+    let kanji_ratio = normalize(metrics.kanji_ratio, KANJI_RATIO_CEILING);
+    let unique_word_ratio = normalize(metrics.unique_word_ratio, 1.0);
+    let single_occurrence_ratio = normalize(metrics.single_occurrence_ratio, 1.0);
+    let vocabulary_rarity = metrics.vocabulary_rarity.map(|x| normalize(x, 1.0));
+
+    let mut weighted_components = vec![
+        (textbox_length, weights.textbox_length),
+        (kanji_ratio, weights.kanji_ratio),
+        (unique_word_ratio, weights.unique_word_ratio),
+        (single_occurrence_ratio, weights.single_occurrence_ratio),
+    ];
+    if let Some(some) = vocabulary_rarity {
+        weighted_components.push((some, weights.vocabulary_rarity));
+    }
+    let weight_sum: f64 = weighted_components.iter().map(|x| x.1).sum();
+    let weighted_sum: f64 = weighted_components.iter().map(|x| x.0 * x.1).sum();
+
+    let score = match weight_sum > 0.0 {
+        true => weighted_sum / weight_sum * 100.0,
+        false => 0.0,
+    };
+
+    return DifficultyScore {
+        score: score,
+        textbox_length: textbox_length,
+        kanji_ratio: kanji_ratio,
+        unique_word_ratio: unique_word_ratio,
+        single_occurrence_ratio: single_occurrence_ratio,
+        vocabulary_rarity: vocabulary_rarity,
+    };
+}
+
+fn normalize(value: f64, ceiling: f64) -> f64 {
+    if !value.is_finite() {
+        return 0.0;
+    }
+    return f64::clamp(value / ceiling, 0.0, 1.0);
+}
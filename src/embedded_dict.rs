@@ -0,0 +1,143 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use sudachi::dic::{
+    header::{Header, HeaderVersion},
+    storage::Storage,
+};
+
+use crate::{cache_handler, dict_handler, error::AnalyzerError, hash_handler};
+
+const EMBEDDED_DICTIONARY: &[u8] = include_bytes!("./system_full.dic.zst");
+pub(crate) const EMBEDDED_DICTIONARY_HASH: &str = env!("EMBEDDED_DICTIONARY_HASH");
+const DICTIONARY_CACHE_PREFIX: &str = "system_full-";
+//Ranges of the cached dictionary hashed to notice damage without reading all of it on startup
+const SAMPLED_RANGE_COUNT: usize = 16;
+const SAMPLED_RANGE_SIZE: usize = 4096;
+
+/// Written next to a cached dictionary once it is complete.
+#[derive(Serialize, Deserialize)]
+struct DictionaryCacheInfo {
+    embedded_dictionary_hash: String,
+    byte_count: u64,
+    /// Modification time of the cached dictionary once it was written. It is never modified in place afterwards.
+    modified_nanos: u64,
+    /// Hash of `SAMPLED_RANGE_COUNT` ranges spread over the decompressed dictionary, from `get_sampled_hash`.
+    sampled_hash: String,
+}
+
+/// Loads the dictionary embedded with the `embedded-dictionary` feature.
+///
+/// The decompressed dictionary is cached in `cache_handler::get_cache_dir` and memory-mapped on later runs.
+/// If the cache can't be used the dictionary is decompressed into memory instead.
+pub fn load_embedded_dictionary() -> Result<Storage, AnalyzerError> {
+    if let Some(some) = load_cached_dictionary() {
+        return Ok(some);
+    }
+    let embedded_dictionary = dict_handler::decode_zstd(EMBEDDED_DICTIONARY)?;
+    let _ = save_cached_dictionary(&embedded_dictionary);
+    return Ok(Storage::Owned(embedded_dictionary));
+}
+
+//The cache is named by the hash of the embedded dictionary so a build with a different dictionary never reads it
+fn get_dictionary_cache_paths() -> Option<(PathBuf, PathBuf)> {
+    let cache_dir = cache_handler::get_cache_dir()?;
+    let name = format!("{}{}", DICTIONARY_CACHE_PREFIX, EMBEDDED_DICTIONARY_HASH);
+    return Some((
+        cache_dir.join(format!("{}.dic", name)),
+        cache_dir.join(format!("{}.json", name)),
+    ));
+}
+
+fn load_cached_dictionary() -> Option<Storage> {
+    let (dictionary_path, info_path) = get_dictionary_cache_paths()?;
+    let info: DictionaryCacheInfo = serde_json::from_slice(&std::fs::read(info_path).ok()?).ok()?;
+    let file = std::fs::File::open(dictionary_path).ok()?;
+    let metadata = file.metadata().ok()?;
+    if info.embedded_dictionary_hash != EMBEDDED_DICTIONARY_HASH
+        || metadata.len() != info.byte_count
+        || get_modified_nanos(&metadata) != info.modified_nanos
+    {
+        return None;
+    }
+    //Safety: cache files are only ever replaced through a rename and never modified in place
+    let mmap = unsafe { memmap2::Mmap::map(&file) }.ok()?;
+    if !check_if_system_dictionary(&mmap) || get_sampled_hash(&mmap) != info.sampled_hash {
+        return None;
+    }
+    return Some(Storage::File(mmap));
+}
+
+fn get_modified_nanos(metadata: &std::fs::Metadata) -> u64 {
+    return metadata
+        .modified()
+        .ok()
+        .and_then(|x| x.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|x| x.as_nanos() as u64)
+        .unwrap_or_default();
+}
+
+//Only touches a few pages of the memory map instead of paging in the whole dictionary
+fn get_sampled_hash(dictionary: &[u8]) -> String {
+    let mut hasher = hash_handler::Fnv1aHasher::default();
+    let stride = (dictionary.len() / SAMPLED_RANGE_COUNT).max(1);
+    for start in (0..dictionary.len())
+        .step_by(stride)
+        .take(SAMPLED_RANGE_COUNT)
+    {
+        hasher.update(&dictionary[start..(start + SAMPLED_RANGE_SIZE).min(dictionary.len())]);
+    }
+    return format!("{:016x}", hasher.finish());
+}
+
+fn check_if_system_dictionary(dictionary: &[u8]) -> bool {
+    return Header::parse(dictionary)
+        .is_ok_and(|x| matches!(x.version, HeaderVersion::SystemDict(_)));
+}
+
+fn save_cached_dictionary(dictionary: &[u8]) -> Result<(), std::io::Error> {
+    let Some((dictionary_path, info_path)) = get_dictionary_cache_paths() else {
+        return Ok(());
+    };
+    remove_stale_cached_dictionaries(&dictionary_path);
+    cache_handler::write_atomically(&dictionary_path, dictionary)?;
+    let info = DictionaryCacheInfo {
+        embedded_dictionary_hash: EMBEDDED_DICTIONARY_HASH.to_string(),
+        byte_count: dictionary.len() as u64,
+        modified_nanos: get_modified_nanos(&std::fs::metadata(&dictionary_path)?),
+        sampled_hash: get_sampled_hash(dictionary),
+    };
+    return cache_handler::write_atomically(&info_path, &serde_json::to_vec(&info)?);
+}
+
+//Dictionaries cached by builds with a different embedded dictionary would otherwise pile up.
+//Temporary files being written by other processes don't match and are left alone
+fn remove_stale_cached_dictionaries(dictionary_path: &PathBuf) {
+    let Some(cache_dir) = dictionary_path.parent() else {
+        return;
+    };
+    let Ok(entries) = std::fs::read_dir(cache_dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        let is_stale = get_cached_dictionary_hash(&file_name.to_string_lossy())
+            .is_some_and(|x| x != EMBEDDED_DICTIONARY_HASH);
+        if is_stale {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+//Only matches the exact `<prefix><hash>.dic` and `<prefix><hash>.json` names of cached dictionaries
+fn get_cached_dictionary_hash(file_name: &str) -> Option<&str> {
+    let name = file_name.strip_prefix(DICTIONARY_CACHE_PREFIX)?;
+    let hash = name
+        .strip_suffix(".dic")
+        .or_else(|| name.strip_suffix(".json"))?;
+    if hash.len() != EMBEDDED_DICTIONARY_HASH.len() || !hash.bytes().all(|x| x.is_ascii_hexdigit())
+    {
+        return None;
+    }
+    return Some(hash);
+}
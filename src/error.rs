@@ -0,0 +1,198 @@
+use serde::Serialize;
+use std::fmt::Display;
+
+use crate::file_handler::MokuroFormat;
+
+/// Errors from reading, analyzing, or writing files.
+#[derive(Debug)]
+pub enum AnalyzerError {
+    /// A file could not be read or written.
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    /// A json file could not be parsed or written.
+    Json {
+        path: String,
+        source: serde_json::Error,
+    },
+    /// A Mokuro file from a version which is not supported failed to parse.
+    MokuroVersion {
+        path: String,
+        version: String,
+        source: serde_json::Error,
+    },
+    /// A json file is valid but does not have the layout of Mokuro output of the analyzed format.
+    NotMokuro { path: String, format: MokuroFormat },
+    /// A line failed to tokenize and was skipped.
+    Tokenize {
+        path: String,
+        line: String,
+        message: String,
+    },
+    /// Words could not be written to the `word_list_raw_writer` of the analyzer.
+    WordListRaw(std::io::Error),
+    /// The tokenizer dictionary could not be loaded.
+    Dictionary(String),
+    /// An option has an invalid value or a file it points to could not be loaded.
+    InvalidOption(String),
+}
+
+/// A skipped file or line as written to `problems.json`.
+#[derive(Debug, Serialize)]
+pub struct Problem {
+    /// One of `read`, `invalid_utf8`, `json`, `mokuro_version`, `not_mokuro`, `tokenize`, or `other`.
+    pub kind: &'static str,
+    pub path: String,
+    /// The line which was skipped. Only set for `tokenize` problems.
+    pub line: Option<String>,
+    pub message: String,
+}
+
+impl AnalyzerError {
+    /// Whether the error only caused a file or line of the input to be skipped instead of stopping the analysis.
+    pub fn is_skipped_input(&self) -> bool {
+        match self {
+            AnalyzerError::Io { .. }
+            | AnalyzerError::Json { .. }
+            | AnalyzerError::MokuroVersion { .. }
+            | AnalyzerError::NotMokuro { .. }
+            | AnalyzerError::Tokenize { .. } => return true,
+            AnalyzerError::WordListRaw(_)
+            | AnalyzerError::Dictionary(_)
+            | AnalyzerError::InvalidOption(_) => return false,
+        }
+    }
+
+    /// Whether the skipped input isn't text to analyze at all, like json files which aren't Mokuro output.
+    /// It is only listed in `problems.json` and doesn't change the exit code or stop strict analyzers.
+    pub fn is_ignored_input(&self) -> bool {
+        return matches!(self, AnalyzerError::NotMokuro { .. });
+    }
+
+    pub fn to_problem(&self) -> Problem {
+        match self {
+            AnalyzerError::Io { path, source } => {
+                return Problem {
+                    kind: match source.kind() {
+                        std::io::ErrorKind::InvalidData => "invalid_utf8",
+                        _ => "read",
+                    },
+                    path: path.clone(),
+                    line: None,
+                    message: source.to_string(),
+                };
+            }
+            AnalyzerError::Json { path, source } => {
+                return Problem {
+                    kind: "json",
+                    path: path.clone(),
+                    line: None,
+                    message: source.to_string(),
+                };
+            }
+            AnalyzerError::MokuroVersion {
+                path,
+                version,
+                source,
+            } => {
+                return Problem {
+                    kind: "mokuro_version",
+                    path: path.clone(),
+                    line: None,
+                    message: format!("Unsupported Mokuro version `{}`: {}", version, source),
+                };
+            }
+            AnalyzerError::NotMokuro { path, .. } => {
+                return Problem {
+                    kind: "not_mokuro",
+                    path: path.clone(),
+                    line: None,
+                    message: self.to_string(),
+                };
+            }
+            AnalyzerError::Tokenize {
+                path,
+                line,
+                message,
+            } => {
+                return Problem {
+                    kind: "tokenize",
+                    path: path.clone(),
+                    line: Some(line.clone()),
+                    message: message.clone(),
+                };
+            }
+            AnalyzerError::WordListRaw(_)
+            | AnalyzerError::Dictionary(_)
+            | AnalyzerError::InvalidOption(_) => {
+                return Problem {
+                    kind: "other",
+                    path: "".to_string(),
+                    line: None,
+                    message: self.to_string(),
+                };
+            }
+        }
+    }
+}
+
+impl Display for AnalyzerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnalyzerError::Io { path, source } => write!(f, "`{}`: {}", path, source),
+            AnalyzerError::Json { path, source } => {
+                write!(f, "`{}`: Invalid json: {}", path, source)
+            }
+            AnalyzerError::MokuroVersion {
+                path,
+                version,
+                source,
+            } => write!(
+                f,
+                "`{}`: Unsupported Mokuro version `{}`: {}",
+                path, version, source
+            ),
+            AnalyzerError::NotMokuro { path, format } => write!(
+                f,
+                "`{}`: Not a Mokuro {}",
+                path,
+                match format {
+                    MokuroFormat::PageJson => "page",
+                    MokuroFormat::VolumeFile => "volume",
+                }
+            ),
+            AnalyzerError::Tokenize {
+                path,
+                line,
+                message,
+            } => write!(
+                f,
+                "`{}`: Line failed to tokenize `{}`: {}",
+                path, line, message
+            ),
+            AnalyzerError::WordListRaw(source) => {
+                write!(f, "Failed to write raw word list: {}", source)
+            }
+            AnalyzerError::Dictionary(message) => {
+                write!(f, "Failed to load tokenizer dictionary: {}", message)
+            }
+            AnalyzerError::InvalidOption(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for AnalyzerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AnalyzerError::Io { source, .. } => return Some(source),
+            AnalyzerError::Json { source, .. } => return Some(source),
+            AnalyzerError::MokuroVersion { source, .. } => return Some(source),
+            AnalyzerError::WordListRaw(source) => return Some(source),
+            AnalyzerError::NotMokuro { .. }
+            | AnalyzerError::Tokenize { .. }
+            | AnalyzerError::Dictionary(_)
+            | AnalyzerError::InvalidOption(_) => return None,
+        }
+    }
+}
@@ -1,11 +1,119 @@
-use serde::Deserialize;
-use std::path::PathBuf;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use serde::{
+    Deserialize,
+    de::{DeserializeOwned, IgnoredAny},
+};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    io::BufRead,
+    path::PathBuf,
+    sync::Mutex,
+};
 
-use crate::utf8_bufreader::Utf8BufReader;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{
+    analyzer,
+    args_parser::AnalysisType,
+    error::AnalyzerError,
+    hash_handler,
+    mmap_line_reader::MmapLineReader,
+    noise_filter::{NoiseFilter, NoiseStats},
+    stats_handler::LayoutStats,
+    utf8_bufreader::{DecodedText, Utf8BufReader},
+};
 
 //https://github.com/WorksApplications/sudachi.rs/blob/d78bf49e8473a5895e542c54f9e7375e9c009e26/sudachi/src/input_text/buffer/mod.rs#L32C27-L32C52
 const SUDACHI_MAX_TOKENIZER_LENGTH: usize = u16::MAX as usize / 4 * 3;
-const DEFAULT_PLAIN_BUFFER_SIZE: usize = 10_000_000;
+//Major and minor versions of Mokuro whose output has been checked to parse
+const SUPPORTED_MOKURO_VERSIONS: [&str; 2] = ["0.1", "0.2"];
+pub const DEFAULT_PLAIN_BUFFER_SIZE: usize = 10_000_000;
+/// Smallest chunk size plain text can be read in.
+pub const MIN_PLAIN_BUFFER_SIZE: usize = 4096;
+//Smallest chunk size an adaptive chunk size goes down to. Smaller chunks mostly add overhead
+const MIN_ADAPTIVE_BUFFER_SIZE: usize = 65_536;
+//Several chunks per thread let threads which finish early take over the rest of a file
+const CHUNKS_PER_THREAD: u64 = 4;
+
+/// Unicode normalization applied to plain text before it is split into lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum TextNormalization {
+    #[default]
+    None,
+    /// Composes characters such as `か` followed by a combining `゙` into `が`.
+    Nfc,
+    /// Also turns full-width ASCII into ASCII and half-width katakana into full-width katakana.
+    Nfkc,
+}
+
+/// Lines of text read from a file along with their layout if the file has one.
+#[derive(Debug, Default)]
+pub struct TextChunk {
+    pub lines: Vec<String>,
+    pub layout: LayoutStats,
+    pub noise: NoiseStats,
+    /// Number of lines or textboxes removed for already appearing earlier in the volume.
+    pub duplicate_line_count: u64,
+    /// Size of the plain text the lines were read from. Only set for plain text files.
+    pub byte_count: u64,
+    /// Number of invalid UTF-8 bytes replaced in the plain text the lines were read from.
+    pub invalid_utf8_byte_count: u64,
+    /// Whether the plain text was cut inside a line to stay within the chunk size, so the lines depend on the chunk size.
+    pub ends_within_line: bool,
+}
+
+impl From<Vec<String>> for TextChunk {
+    fn from(lines: Vec<String>) -> Self {
+        return TextChunk {
+            lines: lines,
+            layout: Default::default(),
+            noise: Default::default(),
+            duplicate_line_count: 0,
+            byte_count: 0,
+            invalid_utf8_byte_count: 0,
+            ends_within_line: false,
+        };
+    }
+}
+
+/// Remembers the hashes of the lines seen in each volume so repeated lines or textboxes can be skipped.
+#[derive(Debug, Default)]
+pub struct LineDeduplicator {
+    seen_lines: Mutex<HashMap<String, HashSet<u64>>>,
+}
+
+impl LineDeduplicator {
+    /// Checks if `line` was already seen in the volume and marks it as seen. Blank lines are never duplicates.
+    pub fn check_if_duplicate(&self, volume_path: &str, line: &str) -> bool {
+        if line.trim().is_empty() {
+            return false;
+        }
+        let line_hash = hash_handler::hash_bytes(line.as_bytes());
+        let mut seen_lines = self
+            .seen_lines
+            .lock()
+            .expect("Failed to get seen lines writer");
+        match seen_lines.get_mut(volume_path) {
+            Some(some) => return !some.insert(line_hash),
+            None => {
+                seen_lines.insert(volume_path.to_string(), HashSet::from([line_hash]));
+                return false;
+            }
+        }
+    }
+
+    /// Removes the lines of a chunk which were already seen in the volume.
+    pub fn remove_duplicates(&self, volume_path: &str, mut text_chunk: TextChunk) -> TextChunk {
+        let line_count = text_chunk.lines.len();
+        text_chunk
+            .lines
+            .retain(|x| !self.check_if_duplicate(volume_path, x));
+        text_chunk.duplicate_line_count += (line_count - text_chunk.lines.len()) as u64;
+        return text_chunk;
+    }
+}
 
 pub fn get_files(directory: &str, extension: &str) -> Vec<std::path::PathBuf> {
     let mut json_files: Vec<std::path::PathBuf> = Default::default();
@@ -16,61 +124,268 @@ pub fn get_files(directory: &str, extension: &str) -> Vec<std::path::PathBuf> {
     {
         let file_name = entry.file_name().to_string_lossy();
 
-        if file_name.to_string().ends_with(extension) {
+        //Directories would otherwise match `--any` without an extension and be reported as unreadable
+        if entry.file_type().is_file() && file_name.to_string().ends_with(extension) {
             json_files.push(entry.into_path());
         }
     }
     return json_files;
 }
 
-pub fn get_json_file_data(filepath: &PathBuf) -> Vec<String> {
+/// Gets the path of the volume a file belongs to. Mokuro `_ocr` json files are single pages so their directory is the volume.
+pub fn get_volume_path(filepath: &PathBuf, analysis_type: &AnalysisType) -> String {
+    let volume_path = match analysis_type {
+        AnalysisType::MokuroJson => filepath.parent().unwrap_or(filepath),
+        AnalysisType::Mokuro | AnalysisType::Any => filepath,
+    };
+    return volume_path.to_string_lossy().to_string();
+}
+
+pub fn get_json_file_data(
+    filepath: &PathBuf,
+    noise_filter: &NoiseFilter,
+    line_deduplicator: Option<&LineDeduplicator>,
+) -> Result<TextChunk, AnalyzerError> {
+    let page = read_mokuro_file::<MokuroJson>(filepath, MokuroFormat::PageJson)?;
+    let volume_path = get_volume_path(filepath, &AnalysisType::MokuroJson);
+    return Ok(get_page_text_chunk(
+        page,
+        noise_filter,
+        line_deduplicator,
+        &volume_path,
+    ));
+}
+
+/// Gets the lines of each page in a `.mokuro` file.
+pub fn get_mokuro_file_data(
+    filepath: &PathBuf,
+    noise_filter: &NoiseFilter,
+    line_deduplicator: Option<&LineDeduplicator>,
+) -> Result<Vec<TextChunk>, AnalyzerError> {
+    let mokuro_file = read_mokuro_file::<MokuroFile>(filepath, MokuroFormat::VolumeFile)?;
+    let volume_path = get_volume_path(filepath, &AnalysisType::Mokuro);
+    let mut pages: Vec<TextChunk> = Default::default();
+    for page in mokuro_file.pages {
+        pages.push(get_page_text_chunk(
+            page,
+            noise_filter,
+            line_deduplicator,
+            &volume_path,
+        ));
+    }
+    return Ok(pages);
+}
+
+//The file is parsed once and then checked for the layout and read from the parsed json.
+//Files from unsupported Mokuro versions which fail to read are reported with their version
+fn read_mokuro_file<T: DeserializeOwned>(
+    filepath: &PathBuf,
+    mokuro_format: MokuroFormat,
+) -> Result<T, AnalyzerError> {
+    let json_data = std::fs::read_to_string(filepath).map_err(|err| AnalyzerError::Io {
+        path: filepath.to_string_lossy().to_string(),
+        source: err,
+    })?;
+    let json_value: serde_json::Value =
+        serde_json::from_str(&json_data).map_err(|err| AnalyzerError::Json {
+            path: filepath.to_string_lossy().to_string(),
+            source: err,
+        })?;
+    drop(json_data);
+    //json which is valid but not an object has no metadata
+    let metadata = MokuroMetadata::deserialize(&json_value).unwrap_or_default();
+    if metadata.get_format() != Some(mokuro_format) {
+        return Err(AnalyzerError::NotMokuro {
+            path: filepath.to_string_lossy().to_string(),
+            format: mokuro_format,
+        });
+    }
+    return T::deserialize(json_value).map_err(|err| {
+        let version = metadata.version.filter(|x| !check_if_supported_version(x));
+        match version {
+            Some(some) => AnalyzerError::MokuroVersion {
+                path: filepath.to_string_lossy().to_string(),
+                version: some,
+                source: err,
+            },
+            None => AnalyzerError::Json {
+                path: filepath.to_string_lossy().to_string(),
+                source: err,
+            },
+        }
+    });
+}
+
+fn check_if_supported_version(version: &str) -> bool {
+    return SUPPORTED_MOKURO_VERSIONS
+        .iter()
+        .any(|x| version == *x || version.starts_with(&(x.to_string() + ".")));
+}
+
+fn get_page_text_chunk(
+    page: MokuroJson,
+    noise_filter: &NoiseFilter,
+    line_deduplicator: Option<&LineDeduplicator>,
+    volume_path: &str,
+) -> TextChunk {
+    let mut layout = LayoutStats::default();
+    let mut noise = NoiseStats::default();
+    let mut duplicate_line_count: u64 = 0;
+    let page_area = page.img_width * page.img_height;
+    let mut text_area: f64 = 0.0;
     let mut lines: Vec<String> = Default::default();
-    let json_data = match std::fs::read_to_string(&filepath) {
-        Ok(ok) => ok,
-        Err(err) => {
-            let filepath_str = filepath.to_str().unwrap_or("failed to display filepath");
-            println!(
-                "Failed to read json file `{}`\nError: `{}`",
-                filepath_str, err
-            );
-            return vec![];
+    for block in page.blocks {
+        let line = block.lines.concat();
+        let char_count = analyzer::filter_non_japanese(line.chars().collect()).len() as u64;
+        if let Some(noise_reason) =
+            noise_filter.get_noise_reason(&line, block.font_size, block.get_box_area())
+        {
+            noise.add(noise_reason, char_count);
+            continue;
         }
-    };
-    match serde_json::from_str::<MokuroJson>(&json_data) {
-        Ok(ok) => {
-            for block in ok.blocks {
-                lines.push(block.lines.concat());
+        if line_deduplicator.is_some_and(|x| x.check_if_duplicate(volume_path, &line)) {
+            duplicate_line_count += 1;
+            continue;
+        }
+        match block.vertical {
+            true => {
+                layout.vertical_box_count += 1;
+                layout.vertical_char_count += char_count;
+            }
+            false => {
+                layout.horizontal_box_count += 1;
+                layout.horizontal_char_count += char_count;
             }
         }
-        Err(_) => {}
+        let font_size = block.font_size.round() as usize;
+        *layout.font_size_box_counts.entry(font_size).or_insert(0) += 1;
+        *layout.font_size_char_counts.entry(font_size).or_insert(0) += char_count;
+        text_area += block.get_text_area();
+        lines.push(line);
     }
-    return lines;
+    if page_area > 0 {
+        layout.text_area += text_area as u64;
+        layout.page_area += page_area;
+        let text_area_percentage = (text_area / page_area as f64 * 100.0).round() as usize;
+        layout.page_text_area_percentages = HashMap::from([(text_area_percentage, 1)]);
+    }
+    return TextChunk {
+        lines: lines,
+        layout: layout,
+        noise: noise,
+        duplicate_line_count: duplicate_line_count,
+        byte_count: 0,
+        invalid_utf8_byte_count: 0,
+        ends_within_line: false,
+    };
 }
 
-pub fn get_mokuro_file_data(filepath: &PathBuf) -> Vec<String> {
-    let mut lines: Vec<String> = Default::default();
-    let json_data = match std::fs::read_to_string(&filepath) {
-        Ok(ok) => ok,
-        Err(err) => {
-            let filepath_str = filepath.to_str().unwrap_or("failed to display filepath");
-            println!(
-                "Failed to read json file `{}`\nError: `{}`",
-                filepath_str, err
-            );
-            return vec![];
+/// Reads only the version, title, and volume info of a Mokuro file along with which layout it has.
+pub fn get_mokuro_metadata(filepath: &PathBuf) -> Result<MokuroMetadata, AnalyzerError> {
+    let file = std::fs::File::open(filepath).map_err(|err| AnalyzerError::Io {
+        path: filepath.to_string_lossy().to_string(),
+        source: err,
+    })?;
+    return serde_json::from_reader(std::io::BufReader::new(file)).map_err(|err| {
+        AnalyzerError::Json {
+            path: filepath.to_string_lossy().to_string(),
+            source: err,
         }
-    };
-    match serde_json::from_str::<MokuroFile>(&json_data) {
-        Ok(ok) => {
-            for page in ok.pages {
-                for block in page.blocks {
-                    lines.push(block.lines.concat());
+    });
+}
+
+/// Removes `.mokuro` files with the same `volume_uuid` as another file. The first file in path order is kept.
+///
+/// Returns the kept files and the removed files along with the file they are a duplicate of.
+pub fn remove_duplicate_volumes(
+    filepaths: Vec<PathBuf>,
+    volume_metadata: &HashMap<String, MokuroMetadata>,
+) -> (Vec<PathBuf>, Vec<(PathBuf, PathBuf)>) {
+    return remove_duplicates_by_key(
+        filepaths,
+        |filepath| {
+            volume_metadata
+                .get(filepath.to_string_lossy().as_ref())
+                .and_then(|x| x.volume_uuid.clone())
+        },
+        |_, _| true,
+    );
+}
+
+/// Removes files with exactly the same contents as another file. The first file in path order is kept.
+///
+/// Returns the kept files and the removed files along with the file they are a duplicate of.
+pub fn remove_duplicate_files(filepaths: Vec<PathBuf>) -> (Vec<PathBuf>, Vec<(PathBuf, PathBuf)>) {
+    let file_hashes: HashMap<PathBuf, (u64, u64)> = filepaths
+        .par_iter()
+        .filter_map(|x| Some((x.clone(), hash_handler::hash_file(x).ok()?)))
+        .collect();
+    //The hash isn't collision resistant so files with the same hash are compared byte by byte
+    return remove_duplicates_by_key(
+        filepaths,
+        |filepath| file_hashes.get(filepath).copied(),
+        check_if_same_contents,
+    );
+}
+
+//Files without a key are always kept. Files with the same key are only duplicates if `is_duplicate` agrees
+pub(crate) fn remove_duplicates_by_key<
+    K: Eq + Hash,
+    F: Fn(&PathBuf) -> Option<K>,
+    D: Fn(&PathBuf, &PathBuf) -> bool,
+>(
+    mut filepaths: Vec<PathBuf>,
+    get_key: F,
+    is_duplicate: D,
+) -> (Vec<PathBuf>, Vec<(PathBuf, PathBuf)>) {
+    filepaths.sort();
+    let mut kept_keys: HashMap<K, Vec<PathBuf>> = Default::default();
+    let mut duplicate_filepaths: Vec<(PathBuf, PathBuf)> = Default::default();
+    let mut kept_filepaths: Vec<PathBuf> = Default::default();
+    for filepath in filepaths {
+        match get_key(&filepath) {
+            Some(some) => {
+                let kept_with_key = kept_keys.entry(some).or_default();
+                match kept_with_key.iter().find(|x| is_duplicate(&filepath, x)) {
+                    Some(kept_filepath) => {
+                        duplicate_filepaths.push((filepath, kept_filepath.clone()))
+                    }
+                    None => {
+                        kept_with_key.push(filepath.clone());
+                        kept_filepaths.push(filepath);
+                    }
                 }
             }
+            None => kept_filepaths.push(filepath),
         }
-        Err(_) => {}
     }
-    return lines;
+    return (kept_filepaths, duplicate_filepaths);
+}
+
+//Files which fail to read are never the same
+pub(crate) fn check_if_same_contents(filepath: &PathBuf, filepath2: &PathBuf) -> bool {
+    let (Ok(file), Ok(file2)) = (
+        std::fs::File::open(filepath),
+        std::fs::File::open(filepath2),
+    ) else {
+        return false;
+    };
+    let mut reader = std::io::BufReader::new(file);
+    let mut reader2 = std::io::BufReader::new(file2);
+    loop {
+        let (Ok(buffer), Ok(buffer2)) = (reader.fill_buf(), reader2.fill_buf()) else {
+            return false;
+        };
+        if buffer.is_empty() || buffer2.is_empty() {
+            return buffer.is_empty() && buffer2.is_empty();
+        }
+        let length = buffer.len().min(buffer2.len());
+        if buffer[..length] != buffer2[..length] {
+            return false;
+        }
+        reader.consume(length);
+        reader2.consume(length);
+    }
 }
 
 fn chunk_utf8_string(input_string: String, chunk_size: usize) -> Vec<String> {
@@ -88,70 +403,243 @@ fn chunk_utf8_string(input_string: String, chunk_size: usize) -> Vec<String> {
     return chunks;
 }
 
+/// Reads plain text files in chunks split into lines. Chunks only end at line breaks unless the file can't be memory-mapped.
 pub struct BufferedPlainLineReader {
-    bufreader: Utf8BufReader,
+    reader: PlainTextReader,
+    normalization: TextNormalization,
+    filepath: PathBuf,
+}
+
+//Files which can't be memory-mapped, such as pipes, are read through a buffer instead
+enum PlainTextReader {
+    Mapped(MmapLineReader),
+    Buffered(Utf8BufReader),
 }
 
 impl BufferedPlainLineReader {
-    pub fn new(file_path: &PathBuf) -> Result<Self, std::io::Error> {
-        Ok(BufferedPlainLineReader {
-            bufreader: Utf8BufReader::new(file_path, DEFAULT_PLAIN_BUFFER_SIZE)?,
-        })
+    pub fn new(file_path: &PathBuf) -> Result<Self, AnalyzerError> {
+        return BufferedPlainLineReader::with_chunk_size(file_path, DEFAULT_PLAIN_BUFFER_SIZE);
+    }
+
+    /// Reads chunks of at most `chunk_size` bytes instead of `DEFAULT_PLAIN_BUFFER_SIZE`.
+    pub fn with_chunk_size(file_path: &PathBuf, chunk_size: usize) -> Result<Self, AnalyzerError> {
+        let reader = match MmapLineReader::new(file_path, chunk_size) {
+            Ok(ok) => PlainTextReader::Mapped(ok),
+            Err(_) => PlainTextReader::Buffered(
+                Utf8BufReader::new(file_path, chunk_size).map_err(|err| AnalyzerError::Io {
+                    path: file_path.to_string_lossy().to_string(),
+                    source: err,
+                })?,
+            ),
+        };
+        return Ok(BufferedPlainLineReader {
+            reader: reader,
+            normalization: TextNormalization::None,
+            filepath: file_path.clone(),
+        });
+    }
+
+    /// Replace each invalid UTF-8 sequence with `U+FFFD` and keep reading instead of stopping with an error.
+    pub fn read_lossy(&mut self, read_lossy: bool) {
+        match &mut self.reader {
+            PlainTextReader::Mapped(some) => some.read_lossy(read_lossy),
+            PlainTextReader::Buffered(some) => some.read_lossy(read_lossy),
+        }
+    }
+
+    pub fn normalize(&mut self, normalization: TextNormalization) {
+        self.normalization = normalization;
     }
 }
 
 impl Iterator for BufferedPlainLineReader {
-    type Item = Vec<String>;
+    type Item = Result<TextChunk, AnalyzerError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut lines: Vec<String> = Default::default();
-        let txt_strings: Vec<String> = self
-            .bufreader
-            .next()?
-            .split("\n")
-            .map(|x| x.to_owned())
-            .collect();
-        for txt_string in txt_strings {
-            let filtered_txt_strings = crate::analyzer::filter_duplicate_ascii(txt_string);
-            for filtered_txt_string in filtered_txt_strings {
-                if filtered_txt_string.len() > SUDACHI_MAX_TOKENIZER_LENGTH {
-                    lines.append(&mut chunk_utf8_string(
-                        filtered_txt_string,
-                        SUDACHI_MAX_TOKENIZER_LENGTH,
-                    ));
-                } else {
-                    lines.push(filtered_txt_string);
-                }
+        let text_chunk = match &mut self.reader {
+            PlainTextReader::Mapped(some) => some
+                .next_chunk()?
+                .map(|x| get_plain_text_chunk(x, self.normalization)),
+            PlainTextReader::Buffered(some) => some
+                .next()?
+                .map(|x| get_plain_text_chunk(x, self.normalization)),
+        };
+        return Some(text_chunk.map_err(|err| AnalyzerError::Io {
+            path: self.filepath.to_string_lossy().to_string(),
+            source: err,
+        }));
+    }
+}
+
+fn get_plain_text_chunk(decoded_text: DecodedText, normalization: TextNormalization) -> TextChunk {
+    return TextChunk {
+        byte_count: decoded_text.byte_count,
+        invalid_utf8_byte_count: decoded_text.replaced_byte_count,
+        ends_within_line: decoded_text.ends_within_line,
+        ..TextChunk::from(split_plain_text(&normalize_text(
+            &decoded_text.text,
+            normalization,
+        )))
+    };
+}
+
+pub fn parse_text_normalization(value: &str) -> Result<TextNormalization, String> {
+    match value {
+        "none" => return Ok(TextNormalization::None),
+        "nfc" => return Ok(TextNormalization::Nfc),
+        "nfkc" => return Ok(TextNormalization::Nfkc),
+        _ => {
+            return Err(format!(
+                "Unknown normalization `{}`. Expected nfkc, nfc, or none",
+                value
+            ));
+        }
+    }
+}
+
+pub fn normalize_text(text: &str, normalization: TextNormalization) -> Cow<'_, str> {
+    match normalization {
+        TextNormalization::Nfc if !unicode_normalization::is_nfc(text) => {
+            return Cow::Owned(text.nfc().collect());
+        }
+        TextNormalization::Nfkc if !unicode_normalization::is_nfkc(text) => {
+            return Cow::Owned(text.nfkc().collect());
+        }
+        _ => return Cow::Borrowed(text),
+    }
+}
+
+/// Gets a chunk size which splits a plain text file into a few chunks for each of `thread_count` threads.
+///
+/// Kept between 64 KB and `DEFAULT_PLAIN_BUFFER_SIZE` so small files aren't split into tiny chunks and large files don't need huge buffers.
+pub fn get_adaptive_chunk_size(file_size: u64, thread_count: usize) -> usize {
+    let chunk_size = file_size / (thread_count as u64 * CHUNKS_PER_THREAD).max(1);
+    return (chunk_size as usize).clamp(MIN_ADAPTIVE_BUFFER_SIZE, DEFAULT_PLAIN_BUFFER_SIZE);
+}
+
+/// Splits plain text into lines short enough for the tokenizer. Runs of ASCII characters also split lines.
+///
+/// Lines end at `\n`, `\r\n`, or `\r`. A byte order mark at the start is removed.
+pub fn split_plain_text(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Default::default();
+    let text = text.strip_prefix('\u{FEFF}').unwrap_or(text);
+    let txt_strings: Vec<String> = text
+        .split('\n')
+        .flat_map(|x| x.strip_suffix('\r').unwrap_or(x).split('\r'))
+        .map(|x| x.to_owned())
+        .collect();
+    for txt_string in txt_strings {
+        let filtered_txt_strings = crate::analyzer::filter_duplicate_ascii(txt_string);
+        for filtered_txt_string in filtered_txt_strings {
+            if filtered_txt_string.len() > SUDACHI_MAX_TOKENIZER_LENGTH {
+                lines.append(&mut chunk_utf8_string(
+                    filtered_txt_string,
+                    SUDACHI_MAX_TOKENIZER_LENGTH,
+                ));
+            } else {
+                lines.push(filtered_txt_string);
             }
         }
-        return Some(lines);
     }
+    return lines;
 }
 
 #[derive(Debug, Deserialize)]
 struct MokuroFile {
-    //version: String,
-    //title: String,
-    //title_uuid: String,
-    //volume: String,
-    //volume_uuid: String,
+    //version, title, title_uuid, volume, and volume_uuid are read by `get_mokuro_metadata`
     pages: Vec<MokuroJson>,
 }
 
+/// Layouts of Mokuro output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MokuroFormat {
+    /// One `_ocr` json file per page with the blocks of the page at the top level. Written by Mokuro 0.1 and kept as a cache by later versions.
+    PageJson,
+    /// One `.mokuro` file per volume with the volume info and a list of pages. Written by Mokuro 0.2 and later.
+    VolumeFile,
+}
+
+impl MokuroFormat {
+    /// Gets the layout of the files searched for by an analysis type. `Any` has no layout.
+    pub fn from_analysis_type(analysis_type: &AnalysisType) -> Option<MokuroFormat> {
+        match analysis_type {
+            AnalysisType::MokuroJson => return Some(MokuroFormat::PageJson),
+            AnalysisType::Mokuro => return Some(MokuroFormat::VolumeFile),
+            AnalysisType::Any => return None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MokuroMetadata {
+    /// Version of Mokuro which wrote the file. Missing from the oldest page json files.
+    pub version: Option<String>,
+    pub title: Option<String>,
+    pub title_uuid: Option<String>,
+    pub volume: Option<String>,
+    pub volume_uuid: Option<String>,
+    //only checked for being present to tell the layouts apart
+    blocks: Option<IgnoredAny>,
+    pages: Option<IgnoredAny>,
+}
+
+impl MokuroMetadata {
+    pub fn get_format(&self) -> Option<MokuroFormat> {
+        match (&self.blocks, &self.pages) {
+            (Some(_), None) => return Some(MokuroFormat::PageJson),
+            (None, Some(_)) => return Some(MokuroFormat::VolumeFile),
+            _ => return None,
+        }
+    }
+
+    /// Key to group volumes of the same series by.
+    pub fn get_series_key(&self) -> Option<&str> {
+        return self.title_uuid.as_deref().or(self.title.as_deref());
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct MokuroJson {
     //version: String,
-    //img_width: i32,
-    //img_height: i32,
+    #[serde(default)]
+    img_width: u64,
+    #[serde(default)]
+    img_height: u64,
     blocks: Vec<MokuroBlock>,
 }
 
 #[derive(Debug, Deserialize)]
 struct MokuroBlock {
-    //#[serde(rename = "box")]
-    //ocr_box: Vec<i32>,
-    //vertical: bool,
-    //font_size: i32,
-    //lines_coords: Vec<Vec<Vec<f32>>>,
+    //x1, y1, x2, y2
+    #[serde(rename = "box", default)]
+    ocr_box: Vec<f64>,
+    #[serde(default)]
+    vertical: bool,
+    #[serde(default)]
+    font_size: f64,
+    //one polygon of x, y points per line
+    #[serde(default)]
+    lines_coords: Vec<Vec<Vec<f64>>>,
     lines: Vec<String>,
 }
+
+impl MokuroBlock {
+    /// Gets the area covered by the lines of the block in pixels. Falls back to the area of the whole box when there are no line coordinates.
+    fn get_text_area(&self) -> f64 {
+        if !self.lines_coords.is_empty() {
+            return self
+                .lines_coords
+                .iter()
+                .map(|x| analyzer::get_polygon_area(x))
+                .sum();
+        }
+        return self.get_box_area();
+    }
+
+    fn get_box_area(&self) -> f64 {
+        match self.ocr_box.as_slice() {
+            [x1, y1, x2, y2] => return (x2 - x1).abs() * (y2 - y1).abs(),
+            _ => return 0.0,
+        }
+    }
+}
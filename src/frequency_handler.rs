@@ -0,0 +1,96 @@
+//! Rank bands of words against a reference frequency list. No list is bundled, it always comes from `--frequency-list`.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::interner::Word;
+
+/// Upper rank bound (inclusive) and display name of each band. Words ranked past the last bound fall into `10k+`.
+const FREQUENCY_BANDS: [(usize, &str); 4] = [
+    (1_000, "top 1k"),
+    (2_000, "1k-2k"),
+    (5_000, "2k-5k"),
+    (10_000, "5k-10k"),
+];
+const OVERFLOW_BAND_NAME: &str = "10k+";
+const UNLISTED_BAND_NAME: &str = "not listed";
+const BAND_COUNT: usize = FREQUENCY_BANDS.len() + 2;
+//Bands from `5k-10k` onwards count as rare vocabulary
+const RARE_BAND_START: usize = 3;
+
+/// Loads a reference frequency list where the line number is the rank of the word.
+///
+/// Only the first tab separated column is used so `word_list.csv` from another run can be used as a reference.
+pub fn load_frequency_list(filepath: &str) -> Result<HashMap<String, usize>, std::io::Error> {
+    let frequency_data = std::fs::read_to_string(filepath)?;
+    let mut frequency_list: HashMap<String, usize> = Default::default();
+    let mut rank = 0;
+    for line in frequency_data.lines() {
+        let word = line.split('\t').next().unwrap_or_default().trim();
+        if word.is_empty() {
+            continue;
+        }
+        rank += 1;
+        frequency_list.entry(word.to_string()).or_insert(rank);
+    }
+    return Ok(frequency_list);
+}
+
+#[derive(Debug, Serialize)]
+pub struct FrequencyBand {
+    pub name: &'static str,
+    pub unique_words: u64,
+    pub total_words: u64,
+}
+
+pub fn get_frequency_bands(
+    word_occurrence_list: &HashMap<Word, u64>,
+    frequency_list: &HashMap<String, usize>,
+) -> Vec<FrequencyBand> {
+    let mut frequency_bands: Vec<FrequencyBand> = get_band_names()
+        .into_iter()
+        .map(|x| FrequencyBand {
+            name: x,
+            unique_words: 0,
+            total_words: 0,
+        })
+        .collect();
+    for (word, occurrences) in word_occurrence_list {
+        let frequency_band =
+            &mut frequency_bands[get_band_index(frequency_list.get(word.as_str()))];
+        frequency_band.unique_words += 1;
+        frequency_band.total_words += occurrences;
+    }
+    return frequency_bands;
+}
+
+/// Share of all words ranked past the top 5k or missing from the reference frequency list.
+pub fn get_vocabulary_rarity(frequency_bands: &Vec<FrequencyBand>) -> f64 {
+    let total_words: u64 = frequency_bands.iter().map(|x| x.total_words).sum();
+    let rare_words: u64 = frequency_bands
+        .iter()
+        .skip(RARE_BAND_START)
+        .map(|x| x.total_words)
+        .sum();
+    return f64::max(rare_words as f64 / total_words as f64, 0.0); // override NaN with 0.0
+}
+
+fn get_band_names() -> [&'static str; BAND_COUNT] {
+    let mut band_names = [OVERFLOW_BAND_NAME; BAND_COUNT];
+    for (i, band) in FREQUENCY_BANDS.iter().enumerate() {
+        band_names[i] = band.1;
+    }
+    band_names[BAND_COUNT - 1] = UNLISTED_BAND_NAME;
+    return band_names;
+}
+
+fn get_band_index(rank: Option<&usize>) -> usize {
+    let rank = match rank {
+        Some(some) => *some,
+        None => return BAND_COUNT - 1,
+    };
+    return FREQUENCY_BANDS
+        .iter()
+        .position(|x| rank <= x.0)
+        .unwrap_or(FREQUENCY_BANDS.len());
+}
@@ -0,0 +1,56 @@
+use std::{io::Read, path::PathBuf};
+
+//https://datatracker.ietf.org/doc/html/draft-eastlake-fnv
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+const HASH_BUFFER_SIZE: usize = 1_000_000;
+
+/// 64-bit FNV-1a hash. Unlike `std::hash::DefaultHasher` the result is stable between builds so it can be stored on disk.
+#[derive(Debug, Clone, Copy)]
+pub struct Fnv1aHasher {
+    hash: u64,
+}
+
+impl Default for Fnv1aHasher {
+    fn default() -> Self {
+        Self {
+            hash: FNV_OFFSET_BASIS,
+        }
+    }
+}
+
+impl Fnv1aHasher {
+    pub fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        return self.hash;
+    }
+}
+
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv1aHasher::default();
+    hasher.update(bytes);
+    return hasher.finish();
+}
+
+/// Hashes the contents of a file. Returns the hash along with the file size in bytes.
+pub fn hash_file(filepath: &PathBuf) -> Result<(u64, u64), std::io::Error> {
+    let mut file = std::fs::File::open(filepath)?;
+    let mut buffer = vec![0u8; HASH_BUFFER_SIZE];
+    let mut hasher = Fnv1aHasher::default();
+    let mut file_size: u64 = 0;
+    loop {
+        let bytes_read = file.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+        file_size += bytes_read as u64;
+    }
+    return Ok((hasher.finish(), file_size));
+}
@@ -0,0 +1,112 @@
+use std::{
+    collections::HashSet,
+    fmt::{Debug, Display},
+    hash::Hash,
+    ops::Deref,
+    sync::{LazyLock, RwLock},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::hash_handler;
+
+//Words are spread over several locks so threads interning different words rarely wait on each other
+const SHARD_COUNT: usize = 64;
+
+static INTERNED_WORDS: LazyLock<[RwLock<HashSet<&'static str>>; SHARD_COUNT]> =
+    LazyLock::new(|| std::array::from_fn(|_| RwLock::default()));
+
+/// An interned word. Every copy of the same word points to the same string so words are compared and hashed by pointer
+/// and stats only store one copy of each word no matter how often it appears.
+///
+/// Interned words are never freed, not even when every analyzer and stats using them are dropped, so memory use grows with
+/// the number of distinct words the process has seen. The vocabulary of even very large amounts of text is small enough for this
+/// not to matter for a single run, but long running processes analyzing unrelated texts keep the words of all of them.
+#[derive(Clone, Copy)]
+pub struct Word(&'static str);
+
+impl Word {
+    pub fn new(word: &str) -> Self {
+        let shard =
+            &INTERNED_WORDS[hash_handler::hash_bytes(word.as_bytes()) as usize % SHARD_COUNT];
+        if let Some(some) = shard.read().expect("Failed to get word reader").get(word) {
+            return Word(some);
+        }
+        let mut shard_lock = shard.write().expect("Failed to get word writer");
+        //Another thread may have interned the word between the locks
+        if let Some(some) = shard_lock.get(word) {
+            return Word(some);
+        }
+        let interned_word: &'static str = Box::leak(word.to_string().into_boxed_str());
+        shard_lock.insert(interned_word);
+        return Word(interned_word);
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        return self.0;
+    }
+}
+
+impl PartialEq for Word {
+    fn eq(&self, other: &Self) -> bool {
+        return std::ptr::eq(self.0, other.0);
+    }
+}
+
+impl Eq for Word {}
+
+impl Hash for Word {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (self.0.as_ptr() as usize).hash(state);
+    }
+}
+
+impl PartialOrd for Word {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl Ord for Word {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        return self.0.cmp(other.0);
+    }
+}
+
+impl Deref for Word {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        return self.0;
+    }
+}
+
+impl From<&str> for Word {
+    fn from(word: &str) -> Self {
+        return Word::new(word);
+    }
+}
+
+impl Display for Word {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return Display::fmt(self.0, f);
+    }
+}
+
+impl Debug for Word {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return Debug::fmt(self.0, f);
+    }
+}
+
+impl Serialize for Word {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        return serializer.serialize_str(self.0);
+    }
+}
+
+impl<'de> Deserialize<'de> for Word {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        return Ok(Word::new(&String::deserialize(deserializer)?));
+    }
+}
@@ -0,0 +1,35 @@
+//! Analysis of Japanese text in Mokuro processed manga and miscellaneous text files.
+//!
+//! Load the tokenizer dictionary once with `dict_handler::make_sudachi_dict`, create an `Analyzer` from it,
+//! then feed it strings, readers, or files to get `AnalysisStats` back. Stats from separate calls can be merged with `AnalysisStats::combine`
+//! and turned into the final numbers with `AnalysisStats::summarize`.
+//!
+//! Words in the stats are interned for the whole process and never freed, even after the `Analyzer` and the stats are dropped.
+//! A long running process analyzing unrelated texts keeps one copy of every distinct word it has seen. See `interner::Word`.
+
+pub mod analyzer;
+pub mod args_parser;
+pub mod cache_handler;
+pub mod dict_handler;
+pub mod difficulty_handler;
+#[cfg(feature = "embedded-dictionary")]
+mod embedded_dict;
+pub mod error;
+pub mod file_handler;
+pub mod frequency_handler;
+mod hash_handler;
+pub mod interner;
+mod mmap_line_reader;
+pub mod noise_filter;
+pub mod progress_handler;
+pub mod stats_cache;
+pub mod stats_handler;
+mod tests;
+pub mod text_analyzer;
+mod type_extensions;
+mod utf8_bufreader;
+mod word_list_raw_writer;
+
+pub use error::AnalyzerError;
+pub use stats_handler::AnalysisStats;
+pub use text_analyzer::{Analyzer, AnalyzerOptions};
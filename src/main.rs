@@ -1,43 +1,161 @@
-use std::{
-    fs::File,
-    io::Read,
-    sync::{Arc, Mutex},
-};
+use std::{collections::HashMap, io::IsTerminal, process::ExitCode, sync::Arc};
 
-use args_parser::AnalysisType;
-use rayon::iter::{IntoParallelRefIterator, ParallelBridge, ParallelIterator};
-use sudachi::{
-    analysis::stateless_tokenizer::StatelessTokenizer, dic::dictionary::JapaneseDictionary,
+use japanese_text_analyzer::{
+    Analyzer, AnalyzerError, AnalyzerOptions, analyzer, args_parser, args_parser::AnalysisType,
+    cache_handler, dict_handler, difficulty_handler, error, file_handler, frequency_handler,
+    noise_filter, progress_handler, stats_cache, stats_handler,
 };
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+const EXIT_FAILURE: u8 = 1;
+const EXIT_INVALID_OPTION: u8 = 2;
+const EXIT_SKIPPED_INPUT: u8 = 3;
+const EXIT_STRICT_FAILURE: u8 = 4;
+//Skipped files are always listed in full
+const MAX_LISTED_SKIPPED_LINES: usize = 20;
+const WORD_LIST_RAW_PATH: &str = "word_list_raw.csv";
+//Only renamed to `WORD_LIST_RAW_PATH` once the analysis succeeded so a failed run leaves the word list of an earlier run
+const WORD_LIST_RAW_TEMP_PATH: &str = "word_list_raw.csv.tmp";
 
-use crate::{stats_handler::AnalysisStats, type_extensions::MutexExtensions};
+/// Errors of `run`. Errors from the analysis stopped by `--strict` are kept apart to exit with their own code.
+enum RunError {
+    Strict(AnalyzerError),
+    Other(AnalyzerError),
+}
 
-mod analyzer;
-mod args_parser;
-mod dict_handler;
-mod file_handler;
-mod stats_handler;
-mod tests;
-mod type_extensions;
-mod utf8_bufreader;
+impl From<AnalyzerError> for RunError {
+    fn from(err: AnalyzerError) -> Self {
+        return RunError::Other(err);
+    }
+}
 
-fn main() {
+fn main() -> ExitCode {
     let args: Vec<String> = std::env::args().collect();
     let parsed_args = args_parser::get_args(args);
     if parsed_args.help {
         println!(include_str!("help_text.txt"));
-        return;
+        return ExitCode::SUCCESS;
     }
 
+    match run(&parsed_args) {
+        Ok(skipped_input) => {
+            if skipped_input.is_empty() {
+                return ExitCode::SUCCESS;
+            }
+            print_skipped_input(&skipped_input);
+            if skipped_input.iter().all(|x| x.is_ignored_input()) {
+                return ExitCode::SUCCESS;
+            }
+            return ExitCode::from(EXIT_SKIPPED_INPUT);
+        }
+        Err(err) => {
+            let _ = std::fs::remove_file(WORD_LIST_RAW_TEMP_PATH);
+            match err {
+                RunError::Strict(err) => {
+                    eprintln!("Error: {}", err);
+                    return ExitCode::from(EXIT_STRICT_FAILURE);
+                }
+                RunError::Other(err) => {
+                    eprintln!("Error: {}", err);
+                    match err {
+                        AnalyzerError::InvalidOption(_) => {
+                            return ExitCode::from(EXIT_INVALID_OPTION);
+                        }
+                        _ => return ExitCode::from(EXIT_FAILURE),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Runs the analysis and writes the output files. Returns the errors of the files and lines which were skipped.
+///
+/// With `--strict` the first file or line which fails is returned as an error instead.
+fn run(
+    parsed_args: &args_parser::JapaneseTextAnalyzerArgs,
+) -> Result<Vec<AnalyzerError>, RunError> {
     let (media_type, enumeration_name) = match parsed_args.analysis_type {
         AnalysisType::MokuroJson => ("manga volumes", "pages"),
         AnalysisType::Mokuro => ("paths", "manga volumes"),
         AnalysisType::Any => ("paths", "files"),
     };
 
+    //The pool has to be set up before anything runs in parallel
+    if let Some(some) = &parsed_args.threads {
+        let thread_count = args_parser::parse_positive_integer("--threads", some)
+            .map_err(AnalyzerError::InvalidOption)?;
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(thread_count)
+            .build_global()
+            .map_err(|err| {
+                AnalyzerError::InvalidOption(format!(
+                    "Failed to start {} threads: {}",
+                    thread_count, err
+                ))
+            })?;
+    }
+    let chunk_size = match &parsed_args.chunk_size {
+        Some(some) => {
+            let chunk_size = args_parser::parse_positive_integer("--chunk-size", some)
+                .map_err(AnalyzerError::InvalidOption)?;
+            if chunk_size < file_handler::MIN_PLAIN_BUFFER_SIZE {
+                return Err(AnalyzerError::InvalidOption(format!(
+                    "`--chunk-size` must be at least {} bytes",
+                    file_handler::MIN_PLAIN_BUFFER_SIZE
+                ))
+                .into());
+            }
+            Some(chunk_size)
+        }
+        None => None,
+    };
+
     println!("Finding {} in {}", media_type, parsed_args.start_path);
     let start_time = std::time::Instant::now();
-    let files = file_handler::get_files(&parsed_args.start_path, &parsed_args.extension);
+    let mut files = file_handler::get_files(&parsed_args.start_path, &parsed_args.extension);
+
+    //Only read up front to skip duplicate volumes. Files which aren't Mokuro volumes are reported when they are analyzed
+    let volume_metadata: HashMap<String, file_handler::MokuroMetadata> =
+        match parsed_args.analysis_type {
+            AnalysisType::Mokuro => files
+                .par_iter()
+                .filter_map(|x| {
+                    let metadata = file_handler::get_mokuro_metadata(x).ok()?;
+                    match metadata.get_format() {
+                        Some(file_handler::MokuroFormat::VolumeFile) => Some((
+                            file_handler::get_volume_path(x, &parsed_args.analysis_type),
+                            metadata,
+                        )),
+                        _ => None,
+                    }
+                })
+                .collect(),
+            AnalysisType::MokuroJson | AnalysisType::Any => HashMap::new(),
+        };
+    let duplicate_volumes;
+    (files, duplicate_volumes) = file_handler::remove_duplicate_volumes(files, &volume_metadata);
+    for (duplicate_path, kept_path) in &duplicate_volumes {
+        println!(
+            "Skipping `{}` (same volume as `{}`)",
+            duplicate_path.to_string_lossy(),
+            kept_path.to_string_lossy()
+        );
+    }
+
+    let duplicate_files;
+    match parsed_args.dedupe_files {
+        true => (files, duplicate_files) = file_handler::remove_duplicate_files(files),
+        false => duplicate_files = vec![],
+    }
+    for (duplicate_path, kept_path) in &duplicate_files {
+        println!(
+            "Skipping `{}` (same contents as `{}`)",
+            duplicate_path.to_string_lossy(),
+            kept_path.to_string_lossy()
+        );
+    }
+
     let file_count = files.len();
     let dir_count = analyzer::count_directories(&files);
     println!(
@@ -49,160 +167,239 @@ fn main() {
         start_time.elapsed().as_millis()
     );
 
+    let frequency_list = match &parsed_args.frequency_list {
+        Some(some) => Some(frequency_handler::load_frequency_list(some).map_err(|err| {
+            AnalyzerError::InvalidOption(format!(
+                "Failed to load frequency list `{}`: {}",
+                some, err
+            ))
+        })?),
+        None => None,
+    };
+    let difficulty_weights = match &parsed_args.difficulty_weights {
+        Some(some) => difficulty_handler::parse_difficulty_weights(some)
+            .map_err(AnalyzerError::InvalidOption)?,
+        None => difficulty_handler::DifficultyWeights::default(),
+    };
+    let normalization = match &parsed_args.normalize {
+        Some(some) => {
+            file_handler::parse_text_normalization(some).map_err(AnalyzerError::InvalidOption)?
+        }
+        None => file_handler::TextNormalization::None,
+    };
+    let noise_filter = match &parsed_args.noise_filter {
+        Some(some) => {
+            noise_filter::parse_noise_filter(some).map_err(AnalyzerError::InvalidOption)?
+        }
+        None => noise_filter::NoiseFilter::default(),
+    };
+
     println!("Loading tokenizer dictionary");
     let start_time = std::time::Instant::now();
-    let dict = dict_handler::make_sudachi_dict().expect("Failed to load tokenizer dictionary");
-    let tokenizer = sudachi::analysis::stateless_tokenizer::StatelessTokenizer::new(&dict);
+    let dictionary_options = dict_handler::DictionaryOptions {
+        system_dict: parsed_args.dict.as_ref().map(std::path::PathBuf::from),
+        user_dicts: parsed_args
+            .user_dicts
+            .iter()
+            .map(std::path::PathBuf::from)
+            .collect(),
+        sudachi_config: parsed_args
+            .sudachi_config
+            .as_ref()
+            .map(std::path::PathBuf::from),
+    };
+    let dict = dict_handler::make_custom_sudachi_dict(&dictionary_options)?;
+    let stats_cache = match parsed_args.no_cache {
+        true => None,
+        false => cache_handler::get_cache_dir().map(|x| {
+            stats_cache::StatsCache::new(
+                x.join("stats"),
+                &dict_handler::get_dictionary_version(&dictionary_options),
+            )
+        }),
+    };
+    let progress = match parsed_args.quiet || !std::io::stderr().is_terminal() {
+        true => None,
+        false => Some(Arc::new(progress_handler::Progress::default())),
+    };
+    let analyzer = Analyzer::new(
+        &dict,
+        AnalyzerOptions {
+            analysis_type: parsed_args.analysis_type,
+            singlethreaded: parsed_args.singlethreaded,
+            chunk_size: chunk_size,
+            noise_filter: noise_filter,
+            dedupe_lines: parsed_args.dedupe_lines,
+            strict: parsed_args.strict,
+            lossy_utf8: parsed_args.lossy_utf8,
+            normalization: normalization,
+            word_list_raw_writer: Some(Box::new(
+                std::fs::File::create(WORD_LIST_RAW_TEMP_PATH).map_err(|err| {
+                    AnalyzerError::Io {
+                        path: WORD_LIST_RAW_TEMP_PATH.to_string(),
+                        source: err,
+                    }
+                })?,
+            )),
+            progress: progress.clone(),
+            stats_cache: stats_cache,
+        },
+    );
     println!("Dictionary loaded ({}ms)", start_time.elapsed().as_millis());
 
     println!("Processing files, running tokenizer, and analyzing results");
     let start_time = std::time::Instant::now();
-    let stats = Arc::new(Mutex::new(stats_handler::AnalysisStats::default()));
-    let word_list_raw_file = Arc::new(Mutex::new(
-        std::fs::File::create(&"word_list_raw.csv").expect("Failed to create word list raw file"),
-    ));
-
-    let process_closure = |lines| {
-        process_lines(
-            lines,
-            &tokenizer,
-            word_list_raw_file.clone(),
-            stats.clone(),
-            file_count,
-            dir_count,
-        );
-    };
-
-    let files_iter_closure = |file_path: &std::path::PathBuf| {
-        match parsed_args.analysis_type {
-            AnalysisType::MokuroJson => {
-                let lines = file_handler::get_json_file_data(&file_path);
-                process_closure(lines);
-            }
-            AnalysisType::Mokuro => {
-                let lines = file_handler::get_mokuro_file_data(&file_path);
-                process_closure(lines);
-            }
-            AnalysisType::Any => {
-                if let Ok(buffered_plain_line_reader) =
-                    file_handler::BufferedPlainLineReader::new(&file_path)
-                {
-                    if parsed_args.singlethreaded {
-                        buffered_plain_line_reader.for_each(process_closure);
-                    } else {
-                        buffered_plain_line_reader
-                            .par_bridge()
-                            .for_each(process_closure);
-                    }
-                }
-            }
-        };
-    };
-
-    if parsed_args.singlethreaded {
-        files.iter().for_each(files_iter_closure);
-    } else {
-        files.par_iter().for_each(files_iter_closure);
+    let progress_reporter = progress.map(progress_handler::ProgressReporter::start);
+    let volume_stats = analyzer.analyze_files(&files);
+    if let Some(progress_reporter) = progress_reporter {
+        progress_reporter.finish();
     }
+    let volume_stats = volume_stats.map_err(|err| match parsed_args.strict {
+        true if err.is_skipped_input() => RunError::Strict(err),
+        _ => RunError::Other(err),
+    })?;
     println!(
         "Tokenizer and analysis finished ({}ms)",
         start_time.elapsed().as_millis()
     );
+    if analyzer.get_cache_hit_count() > 0 {
+        println!(
+            "Reused cached stats of {} unchanged {}",
+            analyzer.get_cache_hit_count(),
+            enumeration_name
+        );
+    }
+    let skipped_input = analyzer.take_errors();
+    analyzer.finish()?;
+    std::fs::rename(WORD_LIST_RAW_TEMP_PATH, WORD_LIST_RAW_PATH).map_err(|err| {
+        AnalyzerError::Io {
+            path: WORD_LIST_RAW_PATH.to_string(),
+            source: err,
+        }
+    })?;
 
-    let mut stats = stats.lock().expect("Failed to get stats reader");
+    let (stats, mut analysis_report) = stats_handler::get_analysis_report(
+        &parsed_args.start_path,
+        volume_stats,
+        &volume_metadata,
+        frequency_list.as_ref(),
+        difficulty_weights,
+    );
+    analysis_report.duplicate_volumes = duplicate_volumes
+        .into_iter()
+        .map(|x| stats_handler::DuplicateFile {
+            path: x.0.to_string_lossy().to_string(),
+            duplicate_of: x.1.to_string_lossy().to_string(),
+        })
+        .collect();
+    analysis_report.non_mokuro_files = skipped_input
+        .iter()
+        .filter_map(|x| match x {
+            AnalyzerError::NotMokuro { path, .. } => Some(path.clone()),
+            _ => None,
+        })
+        .collect();
+    analysis_report.duplicate_files = duplicate_files
+        .into_iter()
+        .map(|x| stats_handler::DuplicateFile {
+            path: x.0.to_string_lossy().to_string(),
+            duplicate_of: x.1.to_string_lossy().to_string(),
+        })
+        .collect();
 
-    let formatted_stats = stats.format_fancy(parsed_args);
+    let formatted_stats = analysis_report.total.format_fancy(parsed_args);
 
     println!("{}", formatted_stats);
 
-    let mut stats_file =
-        std::fs::File::create(&"analysis.txt").expect("Failed to create stats file");
-    std::io::Write::write_all(&mut stats_file, formatted_stats.as_bytes())
-        .expect("Failed to write stats file");
+    write_output_file("analysis.txt", &formatted_stats)?;
+
+    let analysis_report_formatted =
+        serde_json::to_string_pretty(&analysis_report).map_err(|err| AnalyzerError::Json {
+            path: "analysis.json".to_string(),
+            source: err,
+        })?;
+    write_output_file("analysis.json", &analysis_report_formatted)?;
+
+    //Also written when empty so the problems of an earlier run don't stay
+    let problems: Vec<error::Problem> = skipped_input.iter().map(|x| x.to_problem()).collect();
+    let problems_formatted =
+        serde_json::to_string_pretty(&problems).map_err(|err| AnalyzerError::Json {
+            path: "problems.json".to_string(),
+            source: err,
+        })?;
+    write_output_file("problems.json", &problems_formatted)?;
 
     let word_occurrence_list_formatted =
-        analyzer::sort_occurrence_list(stats.word_occurrence_list.clone())
-            .into_iter()
-            .fold(Vec::new(), |mut vec, x| {
-                vec.push(x.0 + "\t" + &x.1.to_string());
-                vec
-            })
-            .join("\n");
-
-    let mut word_list_file =
-        std::fs::File::create(&"word_list.csv").expect("Failed to create word list file");
-    std::io::Write::write_all(
-        &mut word_list_file,
-        word_occurrence_list_formatted.as_bytes(),
-    )
-    .expect("Failed to write word list file");
+        analyzer::format_occurrence_list(&stats.word_occurrence_list);
+
+    write_output_file("word_list.csv", &word_occurrence_list_formatted)?;
 
     let kanji_occurrence_list_formatted =
-        analyzer::sort_occurrence_list(stats.kanji_occurrence_list.clone())
-            .into_iter()
-            .fold(Vec::new(), |mut vec, x| {
-                vec.push(x.0 + "\t" + &x.1.to_string());
-                vec
-            })
-            .join("\n");
-
-    let mut kanji_list_file =
-        std::fs::File::create(&"kanji_list.csv").expect("Failed to create kanji list file");
-    std::io::Write::write_all(
-        &mut kanji_list_file,
-        kanji_occurrence_list_formatted.as_bytes(),
-    )
-    .expect("Failed to write kanji list file");
-}
+        analyzer::format_occurrence_list(&stats.kanji_occurrence_list);
 
-fn process_lines(
-    lines: Vec<String>,
-    tokenizer: &StatelessTokenizer<&JapaneseDictionary>,
-    word_list_raw_file: Arc<Mutex<File>>,
-    stats: Arc<Mutex<AnalysisStats>>,
-    file_count: usize,
-    dir_count: usize,
-) {
-    let morpheme_surfaces = run_tokenization(&lines, &tokenizer);
-    let new_stats = stats_handler::get_stats(lines, morpheme_surfaces, file_count, dir_count);
-    {
-        let word_list_raw_file_lock = &mut word_list_raw_file
-            .lock()
-            .expect("Failed to get word_list_raw writer");
-        std::io::Write::write_all(
-            word_list_raw_file_lock.by_ref(),
-            (new_stats.word_list_raw.join("\n") + "\n").as_bytes(),
-        )
-        .expect("Failed to write word list raw file");
-    }
-    {
-        stats
-            .replace_with(|value| value.combine(new_stats))
-            .unwrap();
+    write_output_file("kanji_list.csv", &kanji_occurrence_list_formatted)?;
+
+    let sentence_lengths_formatted = analyzer::format_length_histogram(
+        "length",
+        vec![
+            ("sentences_by_characters", &stats.sentence_char_lengths),
+            ("sentences_by_words", &stats.sentence_word_lengths),
+        ],
+    );
+
+    write_output_file("sentence_lengths.csv", &sentence_lengths_formatted)?;
+
+    let length_histogram_formatted = analyzer::format_length_histogram(
+        "length",
+        vec![
+            ("textboxes", &stats.box_lengths),
+            ("pages", &stats.page_lengths),
+            ("volumes", &stats.get_volume_length_occurrences()),
+        ],
+    );
+
+    write_output_file("length_histogram.csv", &length_histogram_formatted)?;
+
+    if !matches!(parsed_args.analysis_type, AnalysisType::Any) {
+        let font_size_list_formatted = analyzer::format_length_histogram(
+            "font_size",
+            vec![
+                ("textboxes", &stats.layout.font_size_box_counts),
+                ("characters", &stats.layout.font_size_char_counts),
+            ],
+        );
+
+        write_output_file("font_size_list.csv", &font_size_list_formatted)?;
     }
+    return Ok(skipped_input);
 }
 
-fn run_tokenization(
-    lines: &Vec<String>,
-    tokenizer: &StatelessTokenizer<&JapaneseDictionary>,
-) -> Vec<String> {
-    let mut morpheme_surfaces: Vec<String> = Default::default();
-    for line in lines {
-        let morphemes = match sudachi::analysis::Tokenize::tokenize(
-            tokenizer,
-            line,
-            dict_handler::get_mode(),
-            false,
-        ) {
-            Ok(ok) => ok,
-            Err(err) => {
-                println!("Line failed to tokenize `{}`\nError: `{}`", line, err);
-                continue;
-            }
-        };
-        for morpheme in morphemes.iter() {
-            morpheme_surfaces.push(morpheme.surface().to_string());
-        }
+fn write_output_file(filepath: &str, contents: &str) -> Result<(), AnalyzerError> {
+    return std::fs::write(filepath, contents).map_err(|err| AnalyzerError::Io {
+        path: filepath.to_string(),
+        source: err,
+    });
+}
+
+fn print_skipped_input(skipped_input: &Vec<AnalyzerError>) {
+    let (skipped_lines, skipped_files): (Vec<&AnalyzerError>, Vec<&AnalyzerError>) = skipped_input
+        .iter()
+        .partition(|x| matches!(x, AnalyzerError::Tokenize { .. }));
+    eprintln!(
+        "Skipped {} files and {} lines",
+        skipped_files.len(),
+        skipped_lines.len()
+    );
+    for skipped_file in skipped_files {
+        eprintln!("Skipped file {}", skipped_file);
+    }
+    for skipped_line in skipped_lines.iter().take(MAX_LISTED_SKIPPED_LINES) {
+        eprintln!("Skipped line {}", skipped_line);
+    }
+    if skipped_lines.len() > MAX_LISTED_SKIPPED_LINES {
+        eprintln!(
+            "... and {} more lines",
+            skipped_lines.len() - MAX_LISTED_SKIPPED_LINES
+        );
     }
-    return morpheme_surfaces;
 }
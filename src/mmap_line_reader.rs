@@ -0,0 +1,126 @@
+use std::{fs::File, path::PathBuf};
+
+use memmap2::Mmap;
+
+use crate::utf8_bufreader::{DecodedText, decode_utf8, decode_utf8_lossy};
+
+//Where text without line breaks is split instead
+const SENTENCE_TERMINATORS: [&str; 4] = ["。", "！", "？", "｡"];
+
+/// Reads a UTF8 file through a memory map in chunks which only end at line breaks so no line is split between two chunks.
+///
+/// A chunk without any line break ends after its last sentence instead, and only if it has neither is it split at a character boundary.
+///
+/// Returns the valid part of a chunk with invalid UTF8 followed by an `InvalidData` error unless reading lossy, the same as `Utf8BufReader`.
+///
+/// Chunks will always be read from less than or equal to the provided `chunk_size` bytes.
+pub struct MmapLineReader {
+    mmap: Mmap,
+    chunk_size: usize,
+    //byte offset in the file of the start of the next chunk
+    position: usize,
+    end_of_file: bool,
+    read_lossy: bool,
+}
+
+impl MmapLineReader {
+    /// Fails for files which can't be memory-mapped, such as pipes.
+    pub fn new(file_path: &PathBuf, chunk_size: usize) -> Result<Self, std::io::Error> {
+        let file = File::open(file_path)?;
+        if !file.metadata()?.is_file() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "Only regular files can be memory-mapped",
+            ));
+        }
+        //Safety: the file could still be changed by another process while it is read. Analyzed files are expected to stay unchanged
+        let mmap = unsafe { Mmap::map(&file) }?;
+        return Ok(MmapLineReader {
+            mmap: mmap,
+            chunk_size: chunk_size,
+            position: 0,
+            end_of_file: false,
+            read_lossy: false,
+        });
+    }
+
+    /// Replace each invalid UTF8 sequence with `U+FFFD` and keep reading instead of stopping with an error.
+    pub fn read_lossy(&mut self, read_lossy: bool) {
+        self.read_lossy = read_lossy;
+    }
+
+    /// Gets the next chunk. It is borrowed from the memory map unless invalid UTF8 was replaced so it is only copied once split into lines.
+    ///
+    /// A `\n` a chunk ends at is left out so splitting the chunks into lines gives the same lines as splitting the whole file.
+    /// A `\r` is kept since `file_handler::split_plain_text` already drops it at the end of the text.
+    pub fn next_chunk(&mut self) -> Option<Result<DecodedText<'_>, std::io::Error>> {
+        if self.end_of_file || self.position >= self.mmap.len() {
+            return None;
+        }
+        let remaining = &self.mmap[self.position..];
+        let byte_count = find_chunk_end(remaining, self.chunk_size);
+        let mut chunk = &remaining[..byte_count];
+        let ends_within_line =
+            byte_count < remaining.len() && !matches!(chunk.last(), Some(b'\n' | b'\r'));
+        if byte_count < remaining.len() {
+            chunk = chunk.strip_suffix(b"\n").unwrap_or(chunk);
+        }
+        //Chunks end at character boundaries so nothing is left for the next chunk
+        let decoded_text = match self.read_lossy {
+            true => decode_utf8_lossy(chunk, true),
+            false => decode_utf8(chunk),
+        };
+        if decoded_text.byte_count == chunk.len() as u64 {
+            self.position += byte_count;
+            return Some(Ok(DecodedText {
+                byte_count: byte_count as u64,
+                ends_within_line: ends_within_line,
+                ..decoded_text
+            }));
+        }
+        if decoded_text.byte_count == 0 {
+            self.end_of_file = true;
+            return Some(Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid UTF-8 at byte {}", self.position),
+            )));
+        }
+        self.position += decoded_text.byte_count as usize;
+        //Cut short by invalid UTF8
+        return Some(Ok(DecodedText {
+            ends_within_line: true,
+            ..decoded_text
+        }));
+    }
+}
+
+fn find_chunk_end(bytes: &[u8], chunk_size: usize) -> usize {
+    if bytes.len() <= chunk_size {
+        return bytes.len();
+    }
+    let window = &bytes[..chunk_size];
+    //A `\r` only ends a line by itself when it is not followed by `\n`, which would otherwise start the next chunk as an empty line
+    let line_end = (0..chunk_size)
+        .rev()
+        .find(|x| window[*x] == b'\n' || (window[*x] == b'\r' && bytes[*x + 1] != b'\n'));
+    if let Some(some) = line_end {
+        return some + 1;
+    }
+    let sentence_end = SENTENCE_TERMINATORS
+        .iter()
+        .filter_map(|terminator| {
+            window
+                .windows(terminator.len())
+                .rposition(|x| x == terminator.as_bytes())
+                .map(|x| x + terminator.len())
+        })
+        .max();
+    if let Some(some) = sentence_end {
+        return some;
+    }
+    //UTF8 continuation bytes start with 0b10
+    return (1..=chunk_size)
+        .rev()
+        .find(|x| bytes[*x] & 0xC0 != 0x80)
+        .unwrap_or(chunk_size);
+}
@@ -0,0 +1,178 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{analyzer, hash_handler};
+
+//Small tsu is dropped along with non-Japanese characters so `ドドドッ` counts as a repetition of `ド`
+const IGNORED_NOISE_CHARS: [char; 2] = ['ッ', 'っ'];
+const DEFAULT_MIN_JAPANESE_RATIO: f64 = 0.5;
+const MIN_KANA_REPETITIONS: usize = 3;
+const MAX_KANA_REPETITION_UNIT: usize = 2;
+
+pub const NOISE_REASONS: [&str; 5] = [
+    "font_size",
+    "box_area",
+    "repeated_kana",
+    "onomatopoeia",
+    "japanese_ratio",
+];
+
+/// Decides which Mokuro textboxes are sound effects or OCR noise. Filters which are `None` or `false` are disabled.
+#[derive(Debug, Default)]
+pub struct NoiseFilter {
+    pub min_font_size: Option<f64>,
+    pub max_font_size: Option<f64>,
+    pub min_box_area: Option<f64>,
+    pub repeated_kana: bool,
+    pub onomatopoeia: HashSet<String>,
+    pub min_japanese_ratio: Option<f64>,
+}
+
+/// Parses filters in the format `min_font:12,max_font:80,min_area:400,repeated_kana,japanese_ratio:0.5,onomatopoeia:PATH`.
+///
+/// An empty string enables `repeated_kana` and `japanese_ratio:0.5`.
+pub fn parse_noise_filter(filter_string: &str) -> Result<NoiseFilter, String> {
+    if filter_string.is_empty() {
+        return Ok(NoiseFilter {
+            repeated_kana: true,
+            min_japanese_ratio: Some(DEFAULT_MIN_JAPANESE_RATIO),
+            ..Default::default()
+        });
+    }
+
+    let mut noise_filter = NoiseFilter::default();
+    for setting in filter_string.split(',').filter(|x| !x.is_empty()) {
+        let (name, value) = setting.split_once(':').unwrap_or((setting, ""));
+        let parse_value = || {
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|x| x.is_finite() && *x >= 0.0)
+                .ok_or_else(|| format!("Invalid noise filter value `{}` for `{}`", value, name))
+        };
+        match name {
+            "min_font" => noise_filter.min_font_size = Some(parse_value()?),
+            "max_font" => noise_filter.max_font_size = Some(parse_value()?),
+            "min_area" => noise_filter.min_box_area = Some(parse_value()?),
+            "repeated_kana" => noise_filter.repeated_kana = true,
+            "japanese_ratio" => noise_filter.min_japanese_ratio = Some(parse_value()?),
+            "onomatopoeia" => {
+                noise_filter.onomatopoeia = load_onomatopoeia_list(value).map_err(|err| {
+                    format!("Failed to read onomatopoeia list `{}`: {}", value, err)
+                })?
+            }
+            _ => return Err(format!("Unknown noise filter `{}`", name)),
+        }
+    }
+    return Ok(noise_filter);
+}
+
+fn load_onomatopoeia_list(filepath: &str) -> Result<HashSet<String>, std::io::Error> {
+    return Ok(std::fs::read_to_string(filepath)?
+        .lines()
+        .map(|x| get_noise_text(x))
+        .filter(|x| !x.is_empty())
+        .collect());
+}
+
+impl NoiseFilter {
+    /// Describes the filter settings. Stats cached with a different key were filtered differently.
+    pub fn get_cache_key(&self) -> String {
+        let mut onomatopoeia: Vec<&str> = self.onomatopoeia.iter().map(|x| x.as_str()).collect();
+        onomatopoeia.sort();
+        return format!(
+            "{:?},{:?},{:?},{},{:?},{:016x}",
+            self.min_font_size,
+            self.max_font_size,
+            self.min_box_area,
+            self.repeated_kana,
+            self.min_japanese_ratio,
+            hash_handler::hash_bytes(onomatopoeia.join("\n").as_bytes())
+        );
+    }
+
+    /// Gets the reason a textbox counts as noise or `None` if it does not.
+    pub fn get_noise_reason(
+        &self,
+        text: &str,
+        font_size: f64,
+        box_area: f64,
+    ) -> Option<&'static str> {
+        if self.min_font_size.is_some_and(|x| font_size < x)
+            || self.max_font_size.is_some_and(|x| font_size > x)
+        {
+            return Some("font_size");
+        }
+        if self.min_box_area.is_some_and(|x| box_area < x) {
+            return Some("box_area");
+        }
+        let noise_text = get_noise_text(text);
+        if self.repeated_kana && check_if_repeated_kana(&noise_text) {
+            return Some("repeated_kana");
+        }
+        if self.onomatopoeia.contains(&noise_text) {
+            return Some("onomatopoeia");
+        }
+        if let Some(min_japanese_ratio) = self.min_japanese_ratio {
+            let char_count = text.chars().filter(|x| !x.is_whitespace()).count();
+            let japanese_char_count = analyzer::filter_non_japanese(text.chars().collect()).len();
+            if char_count > 0
+                && (japanese_char_count as f64 / char_count as f64) < min_japanese_ratio
+            {
+                return Some("japanese_ratio");
+            }
+        }
+        return None;
+    }
+}
+
+fn get_noise_text(text: &str) -> String {
+    return analyzer::filter_non_japanese(text.chars().collect())
+        .into_iter()
+        .filter(|x| !IGNORED_NOISE_CHARS.contains(x))
+        .collect();
+}
+
+//A single katakana character or a unit of up to two kana repeated at least three times such as `ドドド` or `ざわざわざわ`
+fn check_if_repeated_kana(noise_text: &str) -> bool {
+    let chars: Vec<char> = noise_text.chars().collect();
+    if chars.is_empty() || !chars.iter().all(|x| analyzer::check_if_kana(*x as u32)) {
+        return false;
+    }
+    if chars.len() == 1 {
+        return analyzer::check_if_katakana(chars[0] as u32);
+    }
+    for unit_length in 1..=MAX_KANA_REPETITION_UNIT {
+        if chars.len() % unit_length == 0
+            && chars.len() / unit_length >= MIN_KANA_REPETITIONS
+            && chars
+                .chunks(unit_length)
+                .all(|x| x == &chars[0..unit_length])
+        {
+            return true;
+        }
+    }
+    return false;
+}
+
+/// Number of textboxes and Japanese characters removed by the noise filter by reason.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct NoiseStats {
+    pub box_counts: HashMap<String, u64>,
+    pub char_counts: HashMap<String, u64>,
+}
+
+impl NoiseStats {
+    pub fn add(&mut self, reason: &str, char_count: u64) {
+        *self.box_counts.entry(reason.to_string()).or_insert(0) += 1;
+        *self.char_counts.entry(reason.to_string()).or_insert(0) += char_count;
+    }
+
+    pub fn combine(self, noise_stats2: NoiseStats) -> NoiseStats {
+        return NoiseStats {
+            box_counts: analyzer::merge_hashmap(self.box_counts, noise_stats2.box_counts),
+            char_counts: analyzer::merge_hashmap(self.char_counts, noise_stats2.char_counts),
+        };
+    }
+}
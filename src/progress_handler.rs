@@ -0,0 +1,154 @@
+use std::{
+    io::Write,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+const BYTE_UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+
+/// Counters of how far an analysis has gotten. Shared between the analyzer and a `ProgressReporter`.
+#[derive(Debug)]
+pub struct Progress {
+    start_time: Instant,
+    files_done: AtomicU64,
+    files_total: AtomicU64,
+    bytes_done: AtomicU64,
+    bytes_total: AtomicU64,
+    lines_done: AtomicU64,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self {
+            start_time: Instant::now(),
+            files_done: Default::default(),
+            files_total: Default::default(),
+            bytes_done: Default::default(),
+            bytes_total: Default::default(),
+            lines_done: Default::default(),
+        }
+    }
+}
+
+impl Progress {
+    /// Adds files to be analyzed. `byte_count` is the size of the plain text files among them.
+    pub fn add_total(&self, file_count: u64, byte_count: u64) {
+        self.files_total.fetch_add(file_count, Ordering::Relaxed);
+        self.bytes_total.fetch_add(byte_count, Ordering::Relaxed);
+    }
+
+    pub fn add_file(&self) {
+        self.files_done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_bytes(&self, byte_count: u64) {
+        self.bytes_done.fetch_add(byte_count, Ordering::Relaxed);
+    }
+
+    pub fn add_lines(&self, line_count: u64) {
+        self.lines_done.fetch_add(line_count, Ordering::Relaxed);
+    }
+
+    /// Formats the progress as a single line such as `[files 3/10] [1.5 MB/4.0 MB] [1200 lines/s] [ETA 1m 05s]`.
+    pub fn format_fancy(&self) -> String {
+        let elapsed = self.start_time.elapsed().as_secs_f64();
+        let files_done = self.files_done.load(Ordering::Relaxed);
+        let files_total = self.files_total.load(Ordering::Relaxed);
+        let bytes_done = self.bytes_done.load(Ordering::Relaxed);
+        let bytes_total = self.bytes_total.load(Ordering::Relaxed);
+        let lines_done = self.lines_done.load(Ordering::Relaxed);
+
+        let mut sections = vec![format!("[files {}/{}]", files_done, files_total)];
+        if bytes_total > 0 {
+            sections.push(format!(
+                "[{}/{}]",
+                format_byte_count(bytes_done),
+                format_byte_count(bytes_total)
+            ));
+        }
+        let lines_per_second = match elapsed > 0.0 {
+            true => lines_done as f64 / elapsed,
+            false => 0.0,
+        };
+        sections.push(format!("[{:.0} lines/s]", lines_per_second));
+
+        //bytes give a finer estimate than files when a few large files make up most of the text
+        let fraction_done = match bytes_total > 0 {
+            true => bytes_done as f64 / bytes_total as f64,
+            false => files_done as f64 / files_total.max(1) as f64,
+        };
+        let eta = match fraction_done > 0.0 {
+            true => format_duration(elapsed / fraction_done - elapsed),
+            false => "?".to_string(),
+        };
+        sections.push(format!("[ETA {}]", eta));
+        return sections.join(" ");
+    }
+}
+
+/// Redraws the progress on stderr from a background thread until `finish` is called.
+pub struct ProgressReporter {
+    stopped: Arc<AtomicBool>,
+    thread: std::thread::JoinHandle<()>,
+}
+
+impl ProgressReporter {
+    pub fn start(progress: Arc<Progress>) -> Self {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = stopped.clone();
+        let thread = std::thread::spawn(move || {
+            while !thread_stopped.load(Ordering::Relaxed) {
+                draw_progress(&progress, false);
+                std::thread::park_timeout(REFRESH_INTERVAL);
+            }
+            draw_progress(&progress, true);
+        });
+        return ProgressReporter {
+            stopped: stopped,
+            thread: thread,
+        };
+    }
+
+    /// Stops redrawing and leaves the final progress on its own line.
+    pub fn finish(self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        self.thread.thread().unpark();
+        let _ = self.thread.join();
+    }
+}
+
+fn draw_progress(progress: &Progress, finished: bool) {
+    let mut stderr = std::io::stderr().lock();
+    //clear the rest of the line in case the previous draw was longer
+    let _ = write!(stderr, "\r{}\x1b[K", progress.format_fancy());
+    if finished {
+        let _ = writeln!(stderr);
+    }
+    let _ = stderr.flush();
+}
+
+fn format_byte_count(byte_count: u64) -> String {
+    let mut size = byte_count as f64;
+    let mut unit_index = 0;
+    while size >= 1000.0 && unit_index < BYTE_UNITS.len() - 1 {
+        size /= 1000.0;
+        unit_index += 1;
+    }
+    match unit_index {
+        0 => return format!("{} {}", byte_count, BYTE_UNITS[0]),
+        _ => return format!("{:.1} {}", size, BYTE_UNITS[unit_index]),
+    }
+}
+
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.max(0.0).round() as u64;
+    match seconds {
+        0..60 => return format!("{}s", seconds),
+        60..3600 => return format!("{}m {:02}s", seconds / 60, seconds % 60),
+        _ => return format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    sync::Mutex,
+    time::UNIX_EPOCH,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{cache_handler, hash_handler, stats_handler::AnalysisStats};
+
+//Bump whenever `AnalysisStats` or how it is counted changes so stats cached by older builds are not reused
+const STATS_CACHE_VERSION: u32 = 10;
+
+/// Stats of previously analyzed files kept between runs so only new or changed files have to be tokenized again.
+///
+/// Each file is stored separately, keyed by its path. A cached file is reused when its size and modification time or content hash still match
+/// and it was analyzed with the same settings, tokenizer mode, and dictionary version.
+///
+/// The raw words of a file are streamed to a separate file next to its stats while it is analyzed so they never have to be held in memory.
+#[derive(Debug)]
+pub struct StatsCache {
+    cache_dir: PathBuf,
+    dictionary_version: String,
+    /// Analyzer settings the stats depend on. Set by the `Analyzer` the cache is passed to.
+    pub(crate) settings_key: String,
+}
+
+/// Identifies the contents of a file at the time it was analyzed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct FileKey {
+    path: String,
+    modified_nanos: u64,
+    size: u64,
+    content_hash: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct StatsCacheEntry {
+    cache_version: u32,
+    settings_key: String,
+    pub(crate) key: FileKey,
+    /// Whether the raw words of the file were stored along with the stats.
+    pub(crate) has_word_list_raw: bool,
+    /// Chunk size the file was read in if a chunk had to be cut inside a line, since its lines then depend on the chunk size.
+    pub(crate) chunk_size: Option<usize>,
+    pub(crate) stats: AnalysisStats,
+}
+
+/// Raw words of a file being analyzed, written to a temporary file until they are stored along with the stats of the file.
+pub(crate) struct WordListRawCacheFile {
+    filepath: PathBuf,
+    temp_filepath: PathBuf,
+    //None after a write failed
+    writer: Mutex<Option<BufWriter<File>>>,
+}
+
+pub(crate) enum CacheLookup {
+    Hit(StatsCacheEntry),
+    /// The file has to be analyzed and can then be stored with this key.
+    Miss(FileKey),
+    /// The file could not be read to look it up.
+    Unavailable,
+}
+
+impl StatsCache {
+    /// `dictionary_version` identifies the loaded dictionaries, such as from `dict_handler::get_dictionary_version`.
+    pub fn new(cache_dir: PathBuf, dictionary_version: &str) -> Self {
+        return StatsCache {
+            cache_dir: cache_dir,
+            dictionary_version: dictionary_version.to_string(),
+            settings_key: "".to_string(),
+        };
+    }
+
+    pub(crate) fn lookup(&self, filepath: &PathBuf) -> CacheLookup {
+        let path = std::fs::canonicalize(filepath)
+            .unwrap_or_else(|_| filepath.clone())
+            .to_string_lossy()
+            .to_string();
+        let Ok(metadata) = std::fs::metadata(filepath) else {
+            return CacheLookup::Unavailable;
+        };
+        let modified_nanos = metadata
+            .modified()
+            .ok()
+            .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+            .map(|x| x.as_nanos() as u64)
+            .unwrap_or_default();
+
+        let entry = self.read_entry(&path).filter(|x| {
+            x.cache_version == STATS_CACHE_VERSION
+                && x.settings_key == self.get_full_settings_key()
+                && x.key.size == metadata.len()
+        });
+        let entry = match entry {
+            Some(some) if some.key.modified_nanos == modified_nanos => {
+                return CacheLookup::Hit(some);
+            }
+            other => other,
+        };
+
+        //A file which was touched or copied without changing keeps its stats
+        let Ok((content_hash, size)) = hash_handler::hash_file(filepath) else {
+            return CacheLookup::Unavailable;
+        };
+        let file_key = FileKey {
+            path: path,
+            modified_nanos: modified_nanos,
+            size: size,
+            content_hash: content_hash,
+        };
+        match entry {
+            Some(mut some) if some.key.content_hash == content_hash && some.key.size == size => {
+                some.key = file_key;
+                let _ = self.store(&mut some);
+                return CacheLookup::Hit(some);
+            }
+            _ => return CacheLookup::Miss(file_key),
+        }
+    }
+
+    pub(crate) fn store(&self, entry: &mut StatsCacheEntry) -> Result<(), std::io::Error> {
+        entry.cache_version = STATS_CACHE_VERSION;
+        entry.settings_key = self.get_full_settings_key();
+        return cache_handler::write_atomically(
+            &self.get_entry_path(&entry.key.path),
+            &serde_json::to_vec(entry)?,
+        );
+    }
+
+    /// Stores the entry along with the raw words of the file. Nothing is stored if writing the words failed.
+    pub(crate) fn store_with_word_list_raw(
+        &self,
+        entry: &mut StatsCacheEntry,
+        word_list_raw_file: WordListRawCacheFile,
+    ) -> Result<(), std::io::Error> {
+        word_list_raw_file.persist()?;
+        entry.has_word_list_raw = true;
+        return self.store(entry);
+    }
+
+    pub(crate) fn create_word_list_raw_file(
+        &self,
+        key: &FileKey,
+    ) -> Result<WordListRawCacheFile, std::io::Error> {
+        std::fs::create_dir_all(&self.cache_dir)?;
+        let filepath = self.get_word_list_raw_path(&key.path);
+        let mut temp_filepath = filepath.as_os_str().to_owned();
+        temp_filepath.push(format!(".{}.tmp", std::process::id()));
+        let temp_filepath = PathBuf::from(temp_filepath);
+        let file = File::create(&temp_filepath)?;
+        return Ok(WordListRawCacheFile {
+            filepath: filepath,
+            temp_filepath: temp_filepath,
+            writer: Mutex::new(Some(BufWriter::new(file))),
+        });
+    }
+
+    /// Opens the raw words stored along with the entry, if there are any.
+    pub(crate) fn open_word_list_raw(&self, entry: &StatsCacheEntry) -> Option<File> {
+        if !entry.has_word_list_raw {
+            return None;
+        }
+        return File::open(self.get_word_list_raw_path(&entry.key.path)).ok();
+    }
+
+    fn read_entry(&self, path: &str) -> Option<StatsCacheEntry> {
+        let entry: StatsCacheEntry =
+            serde_json::from_slice(&std::fs::read(self.get_entry_path(path)).ok()?).ok()?;
+        //Two paths may share a hash
+        return Some(entry).filter(|x| x.key.path == path);
+    }
+
+    fn get_entry_path(&self, path: &str) -> PathBuf {
+        return self.cache_dir.join(format!(
+            "{:016x}.json",
+            hash_handler::hash_bytes(path.as_bytes())
+        ));
+    }
+
+    fn get_word_list_raw_path(&self, path: &str) -> PathBuf {
+        return self.cache_dir.join(format!(
+            "{:016x}.words",
+            hash_handler::hash_bytes(path.as_bytes())
+        ));
+    }
+
+    fn get_full_settings_key(&self) -> String {
+        return format!("{}|{}", self.dictionary_version, self.settings_key);
+    }
+}
+
+impl StatsCacheEntry {
+    pub(crate) fn new(key: FileKey, stats: AnalysisStats, chunk_size: Option<usize>) -> Self {
+        return StatsCacheEntry {
+            cache_version: STATS_CACHE_VERSION,
+            settings_key: "".to_string(),
+            key: key,
+            has_word_list_raw: false,
+            chunk_size: chunk_size,
+            stats: stats,
+        };
+    }
+}
+
+impl WordListRawCacheFile {
+    /// Appends words formatted by `word_list_raw_writer::format_word_list_raw`. Chunks are written in the order they're analyzed, which is only the text order in singlethreaded runs.
+    pub(crate) fn write(&self, word_list_raw: &str) {
+        let mut writer = self.writer.lock().expect("Failed to get words writer");
+        if let Some(some) = writer.as_mut()
+            && some.write_all(word_list_raw.as_bytes()).is_err()
+        {
+            *writer = None;
+        }
+    }
+
+    fn persist(self) -> Result<(), std::io::Error> {
+        let writer = self
+            .writer
+            .lock()
+            .expect("Failed to get words writer")
+            .take()
+            .ok_or_else(|| std::io::Error::other("Failed to write the raw words to the cache"))?;
+        writer.into_inner()?.sync_all()?;
+        return std::fs::rename(&self.temp_filepath, &self.filepath);
+    }
+}
+
+impl Drop for WordListRawCacheFile {
+    //The temporary file is already gone once it was persisted
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.temp_filepath);
+    }
+}
@@ -1,127 +1,139 @@
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 use crate::{
-    analyzer,
+    analyzer::{self, CharClass, LengthSummary},
     args_parser::{AnalysisType, JapaneseTextAnalyzerArgs},
+    difficulty_handler::{self, DifficultyMetrics, DifficultyScore, DifficultyWeights},
+    file_handler::{MokuroMetadata, TextChunk},
+    frequency_handler::{self, FrequencyBand},
+    interner::Word,
+    noise_filter::{self, NoiseStats},
+    type_extensions::HashMapExtensions,
 };
 
+/// Where the lines passed to `get_stats` came from.
+#[derive(Debug, Clone)]
+pub struct TextSource {
+    pub volume_path: String,
+    /// Whether the lines make up exactly one full page.
+    pub is_page: bool,
+}
+
 pub fn get_stats(
-    lines: Vec<String>,
-    morpheme_surfaces: Vec<String>,
-    json_file_count: usize,
-    json_dir_count: usize,
+    text_chunk: TextChunk,
+    line_morpheme_surfaces: Vec<Vec<Word>>,
+    text_source: &TextSource,
 ) -> AnalysisStats {
-    let characters = morpheme_surfaces.join("");
-    let filtered_morphemes = analyzer::filter_blacklisted(morpheme_surfaces);
-
-    let word_occurrence_list = analyzer::generate_occurrence_list(&filtered_morphemes);
+    let lines = text_chunk.lines;
+    let sentence_lengths: Vec<(usize, usize)> = lines
+        .iter()
+        .zip(&line_morpheme_surfaces)
+        .flat_map(|x| analyzer::get_sentence_lengths(x.0, x.1))
+        .collect();
+    let sentence_char_lengths: Vec<usize> = sentence_lengths.iter().map(|x| x.0).collect();
+    let sentence_word_lengths: Vec<usize> = sentence_lengths.iter().map(|x| x.1).collect();
 
-    let japanese_characters = analyzer::filter_non_japanese(characters.chars().collect());
-    let kanji_characters: Vec<char> = analyzer::filter_non_kanji(characters.chars().collect());
-    let kanji_occurrence_list = analyzer::generate_occurrence_list(&kanji_characters);
-    let mut unique_kanji_characters: Vec<char> = kanji_characters.clone();
-    unique_kanji_characters.sort();
-    unique_kanji_characters.dedup();
+    let mut word_occurrence_list: HashMap<Word, u64> = HashMap::new();
+    let mut kanji_occurrence_list: HashMap<char, u64> = HashMap::new();
+    let mut char_class_counts = [0; CharClass::ALL.len()];
+    let mut word_count = 0;
+    let mut char_count = 0;
+    let mut kanji_count = 0;
+    for morpheme_surface in line_morpheme_surfaces.iter().flatten() {
+        for character in morpheme_surface.chars() {
+            let Some(char_class) = analyzer::get_char_class(character as u32) else {
+                continue;
+            };
+            char_class_counts[char_class as usize] += 1;
+            if char_class.is_japanese() {
+                char_count += 1;
+            }
+            if char_class == CharClass::Kanji {
+                kanji_count += 1;
+                *kanji_occurrence_list.entry(character).or_insert(0) += 1;
+            }
+        }
+        if !analyzer::check_if_blacklisted(morpheme_surface) {
+            word_count += 1;
+            *word_occurrence_list.entry(*morpheme_surface).or_insert(0) += 1;
+        }
+    }
 
-    let box_length = analyzer::get_avg_len(lines).unwrap_or_default();
+    let box_lengths = analyzer::get_line_lengths(&lines);
+    let page_lengths = match text_source.is_page {
+        true => HashMap::from([(char_count as usize, 1)]),
+        false => HashMap::new(),
+    };
 
     return AnalysisStats {
-        char_count: japanese_characters.len() as u64,
-        kanji_count: kanji_characters.len() as u64,
-        unique_kanji: HashSet::from_iter(unique_kanji_characters.iter().cloned()),
-        word_count: filtered_morphemes.len() as u64,
-        unique_words: HashSet::from_iter(word_occurrence_list.iter().map(|x| x.0.to_owned())),
-        volume_count: json_dir_count,
-        avg_volume_length: japanese_characters.len() as f64 / json_dir_count as f64,
-        page_count: json_file_count,
-        avg_page_length: japanese_characters.len() as f64 / json_file_count as f64,
-        avg_box_length: box_length.average as f64,
-        shortest_box_length: box_length.shortest,
-        longest_box_length: box_length.longest,
-        box_count: box_length.length as u64,
-
-        word_list_raw: filtered_morphemes,
+        char_count: char_count,
+        kanji_count: kanji_count,
+        word_count: word_count,
+        sentence_char_lengths: analyzer::generate_occurrence_list(&sentence_char_lengths),
+        sentence_word_lengths: analyzer::generate_occurrence_list(&sentence_word_lengths),
+        box_lengths: analyzer::generate_occurrence_list(&box_lengths),
+        page_lengths: page_lengths,
+        volume_lengths: HashMap::from([(text_source.volume_path.clone(), char_count)]),
+        layout: text_chunk.layout,
+        noise: text_chunk.noise,
+        duplicate_line_count: text_chunk.duplicate_line_count,
+        invalid_utf8_byte_count: text_chunk.invalid_utf8_byte_count,
+        char_class_counts: char_class_counts,
         kanji_occurrence_list: kanji_occurrence_list,
         word_occurrence_list: word_occurrence_list,
     };
 }
 
-#[derive(Debug)]
+/// Raw totals of an analysis. Everything is stored as counts or occurrence lists so stats can be combined in any order and grouping.
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct AnalysisStats {
     pub char_count: u64,
     pub kanji_count: u64,
-    pub unique_kanji: HashSet<char>,
     pub word_count: u64,
-    pub unique_words: HashSet<String>,
-    pub volume_count: usize,
-    pub avg_volume_length: f64,
-    pub page_count: usize,
-    pub avg_page_length: f64,
-    pub avg_box_length: f64,
-    pub shortest_box_length: usize,
-    pub longest_box_length: usize,
-    pub box_count: u64,
-
-    pub word_list_raw: Vec<String>,
+    pub sentence_char_lengths: HashMap<usize, u64>,
+    pub sentence_word_lengths: HashMap<usize, u64>,
+    pub box_lengths: HashMap<usize, u64>,
+    pub page_lengths: HashMap<usize, u64>,
+    /// Length in characters of each volume by volume path.
+    pub volume_lengths: HashMap<String, u64>,
+    pub layout: LayoutStats,
+    pub noise: NoiseStats,
+    pub duplicate_line_count: u64,
+    /// Invalid UTF-8 bytes replaced while reading plain text lossy.
+    pub invalid_utf8_byte_count: u64,
+    /// Number of characters of each `analyzer::CharClass`, indexed by the class.
+    pub char_class_counts: [u64; CharClass::ALL.len()],
+    /// Unique kanji are the keys of this list.
     pub kanji_occurrence_list: HashMap<char, u64>,
-    pub word_occurrence_list: HashMap<String, u64>,
-}
-
-impl Default for AnalysisStats {
-    fn default() -> Self {
-        Self {
-            char_count: Default::default(),
-            kanji_count: Default::default(),
-            unique_kanji: Default::default(),
-            word_count: Default::default(),
-            unique_words: Default::default(),
-            volume_count: Default::default(),
-            avg_volume_length: Default::default(),
-            page_count: Default::default(),
-            avg_page_length: Default::default(),
-            avg_box_length: Default::default(),
-            shortest_box_length: usize::MAX,
-            longest_box_length: Default::default(),
-            box_count: Default::default(),
-            word_list_raw: Default::default(),
-            kanji_occurrence_list: Default::default(),
-            word_occurrence_list: Default::default(),
-        }
-    }
+    /// Unique words are the keys of this list.
+    pub word_occurrence_list: HashMap<Word, u64>,
 }
 
 impl AnalysisStats {
     pub fn combine(self, stats2: AnalysisStats) -> AnalysisStats {
-        let mut new_stats1_unique_kanji = self.unique_kanji;
-        new_stats1_unique_kanji.extend(stats2.unique_kanji);
-
-        let mut new_stats1_unique_words = self.unique_words;
-        new_stats1_unique_words.extend(stats2.unique_words);
-
         return AnalysisStats {
             char_count: self.char_count + stats2.char_count,
             kanji_count: self.kanji_count + stats2.kanji_count,
-            unique_kanji: new_stats1_unique_kanji,
             word_count: self.word_count + stats2.word_count,
-            unique_words: new_stats1_unique_words,
-            volume_count: stats2.volume_count,
-            avg_volume_length: self.avg_volume_length + stats2.avg_volume_length,
-            page_count: stats2.page_count,
-            avg_page_length: self.avg_page_length + stats2.avg_page_length,
-            avg_box_length: f64::max(
-                (self.avg_box_length * self.box_count as f64
-                    + stats2.avg_box_length * stats2.box_count as f64)
-                    / (self.box_count + stats2.box_count) as f64,
-                0.0, // cure potential NaN corruption by overriding NaNs with 0.0
+            sentence_char_lengths: analyzer::merge_hashmap(
+                self.sentence_char_lengths,
+                stats2.sentence_char_lengths,
             ),
-            shortest_box_length: analyzer::bounded_min(
-                self.shortest_box_length,
-                stats2.shortest_box_length,
-                1,
+            sentence_word_lengths: analyzer::merge_hashmap(
+                self.sentence_word_lengths,
+                stats2.sentence_word_lengths,
             ),
-            longest_box_length: usize::max(self.longest_box_length, stats2.longest_box_length),
-            box_count: self.box_count + stats2.box_count,
-            word_list_raw: Vec::new(),
+            box_lengths: analyzer::merge_hashmap(self.box_lengths, stats2.box_lengths),
+            page_lengths: analyzer::merge_hashmap(self.page_lengths, stats2.page_lengths),
+            volume_lengths: analyzer::merge_hashmap(self.volume_lengths, stats2.volume_lengths),
+            layout: self.layout.combine(stats2.layout),
+            noise: self.noise.combine(stats2.noise),
+            duplicate_line_count: self.duplicate_line_count + stats2.duplicate_line_count,
+            invalid_utf8_byte_count: self.invalid_utf8_byte_count + stats2.invalid_utf8_byte_count,
+            char_class_counts: std::array::from_fn(|x| {
+                self.char_class_counts[x] + stats2.char_class_counts[x]
+            }),
             kanji_occurrence_list: analyzer::merge_hashmap(
                 self.kanji_occurrence_list,
                 stats2.kanji_occurrence_list,
@@ -133,70 +145,542 @@ impl AnalysisStats {
         };
     }
 
-    pub fn format_fancy(&mut self, parsed_args: JapaneseTextAnalyzerArgs) -> String {
-        let format_specific_stats = match parsed_args.analysis_type {
-            AnalysisType::MokuroJson => format!(
-                "{}{:.0} ({} total volumes)\n{}{:.0} ({} total pages)\n{}{:.0} (shortest: {}) (longest: {}) ({} total textboxes)",
-                "Average volume length in characters: ",
-                self.avg_volume_length,
-                self.volume_count,
-                "Average page length in characters: ",
-                self.avg_page_length,
-                self.page_count,
-                "Average textbox length in characters: ",
-                self.avg_box_length,
-                self.shortest_box_length,
-                self.longest_box_length,
-                self.box_count
+    /// Gets how many volumes there are of each length in characters.
+    pub fn get_volume_length_occurrences(&self) -> HashMap<usize, u64> {
+        let volume_lengths: Vec<usize> =
+            self.volume_lengths.values().map(|x| *x as usize).collect();
+        return analyzer::generate_occurrence_list(&volume_lengths);
+    }
+
+    /// Whether nothing was counted, such as for a file which failed before any of its text was read.
+    pub fn is_empty(&self) -> bool {
+        return self.char_count == 0
+            && self.box_lengths.is_empty()
+            && self.page_lengths.is_empty()
+            && self.noise.box_counts.is_empty()
+            && self.duplicate_line_count == 0
+            && self.invalid_utf8_byte_count == 0;
+    }
+
+    pub fn summarize(
+        &self,
+        path: &str,
+        frequency_list: Option<&HashMap<String, usize>>,
+        difficulty_weights: &DifficultyWeights,
+    ) -> AnalysisSummary {
+        let unique_word_count = self.word_occurrence_list.len();
+        let classified_char_count: u64 = self.char_class_counts.iter().sum();
+        let unique_kanji_count = self.kanji_occurrence_list.len();
+        let word_count_single_occurrence =
+            analyzer::count_single_occurrences(&self.word_occurrence_list);
+        let kanji_count_single_occurrence =
+            analyzer::count_single_occurrences(&self.kanji_occurrence_list);
+        let frequency_bands = frequency_list.map(|frequency_list| {
+            frequency_handler::get_frequency_bands(&self.word_occurrence_list, frequency_list)
+        });
+
+        let box_lengths = analyzer::summarize_lengths(&self.box_lengths);
+        let difficulty_metrics = DifficultyMetrics {
+            avg_box_length: box_lengths.average,
+            kanji_ratio: self.kanji_count as f64 / self.char_count as f64,
+            unique_word_ratio: unique_word_count as f64 / self.word_count as f64,
+            single_occurrence_ratio: word_count_single_occurrence as f64 / unique_word_count as f64,
+            vocabulary_rarity: frequency_bands
+                .as_ref()
+                .map(|x| frequency_handler::get_vocabulary_rarity(x)),
+        };
+
+        return AnalysisSummary {
+            path: path.to_string(),
+            title: None,
+            volume: None,
+            char_count: self.char_count,
+            kanji_count: self.kanji_count,
+            unique_kanji_count: unique_kanji_count,
+            kanji_count_single_occurrence: kanji_count_single_occurrence,
+            word_count: self.word_count,
+            unique_word_count: unique_word_count,
+            word_count_single_occurrence: word_count_single_occurrence,
+            sentence_char_lengths: analyzer::summarize_lengths(&self.sentence_char_lengths),
+            sentence_word_lengths: analyzer::summarize_lengths(&self.sentence_word_lengths),
+            box_lengths: box_lengths,
+            page_lengths: analyzer::summarize_lengths(&self.page_lengths),
+            volume_lengths: analyzer::summarize_lengths(&self.get_volume_length_occurrences()),
+            empty_page_count: *self.page_lengths.get(&0).unwrap_or(&0),
+            layout: self.layout.summarize(),
+            filtered_box_counts: self.noise.box_counts.clone(),
+            filtered_char_counts: self.noise.char_counts.clone(),
+            duplicate_line_count: self.duplicate_line_count,
+            invalid_utf8_byte_count: self.invalid_utf8_byte_count,
+            char_class_counts: CharClass::ALL
+                .iter()
+                .map(|x| {
+                    (
+                        x.get_name().to_string(),
+                        self.char_class_counts[*x as usize],
+                    )
+                })
+                .collect(),
+            char_class_ratios: CharClass::ALL
+                .iter()
+                .map(|x| {
+                    (
+                        x.get_name().to_string(),
+                        f64::max(
+                            self.char_class_counts[*x as usize] as f64
+                                / classified_char_count as f64,
+                            0.0,
+                        ), // override NaN with 0.0
+                    )
+                })
+                .collect(),
+            frequency_bands: frequency_bands,
+            difficulty: difficulty_handler::get_difficulty_score(
+                difficulty_metrics,
+                difficulty_weights,
+            ),
+        };
+    }
+}
+
+/// Textbox orientation, size, and placement totals. Only available for Mokuro files.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LayoutStats {
+    pub vertical_box_count: u64,
+    pub horizontal_box_count: u64,
+    pub vertical_char_count: u64,
+    pub horizontal_char_count: u64,
+    /// Area covered by text in pixels. Only counted on pages with known dimensions.
+    pub text_area: u64,
+    /// Area of all pages with known dimensions in pixels.
+    pub page_area: u64,
+    /// Number of pages by the percentage of the page covered by text.
+    pub page_text_area_percentages: HashMap<usize, u64>,
+    /// Number of textboxes by font size in pixels.
+    pub font_size_box_counts: HashMap<usize, u64>,
+    /// Number of Japanese characters by font size in pixels.
+    pub font_size_char_counts: HashMap<usize, u64>,
+}
+
+impl LayoutStats {
+    pub fn combine(self, layout2: LayoutStats) -> LayoutStats {
+        return LayoutStats {
+            vertical_box_count: self.vertical_box_count + layout2.vertical_box_count,
+            horizontal_box_count: self.horizontal_box_count + layout2.horizontal_box_count,
+            vertical_char_count: self.vertical_char_count + layout2.vertical_char_count,
+            horizontal_char_count: self.horizontal_char_count + layout2.horizontal_char_count,
+            text_area: self.text_area + layout2.text_area,
+            page_area: self.page_area + layout2.page_area,
+            page_text_area_percentages: analyzer::merge_hashmap(
+                self.page_text_area_percentages,
+                layout2.page_text_area_percentages,
+            ),
+            font_size_box_counts: analyzer::merge_hashmap(
+                self.font_size_box_counts,
+                layout2.font_size_box_counts,
+            ),
+            font_size_char_counts: analyzer::merge_hashmap(
+                self.font_size_char_counts,
+                layout2.font_size_char_counts,
+            ),
+        };
+    }
+
+    pub fn summarize(&self) -> LayoutSummary {
+        return LayoutSummary {
+            vertical_box_count: self.vertical_box_count,
+            horizontal_box_count: self.horizontal_box_count,
+            vertical_char_count: self.vertical_char_count,
+            horizontal_char_count: self.horizontal_char_count,
+            text_area_ratio: f64::max(self.text_area as f64 / self.page_area as f64, 0.0), // override NaN with 0.0
+            page_text_area_percentages: analyzer::summarize_lengths(
+                &self.page_text_area_percentages,
             ),
+            font_sizes: analyzer::summarize_lengths(&self.font_size_box_counts),
+        };
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LayoutSummary {
+    pub vertical_box_count: u64,
+    pub horizontal_box_count: u64,
+    pub vertical_char_count: u64,
+    pub horizontal_char_count: u64,
+    /// Text area out of page area over all pages with known dimensions.
+    pub text_area_ratio: f64,
+    pub page_text_area_percentages: LengthSummary,
+    /// Font sizes in pixels by textbox.
+    pub font_sizes: LengthSummary,
+}
+
+impl LayoutSummary {
+    pub fn format_fancy(&self) -> String {
+        let box_count = self.vertical_box_count + self.horizontal_box_count;
+        let char_count = self.vertical_char_count + self.horizontal_char_count;
+        return format!(
+            "{}{} ({} of textboxes) ({} of characters)\n{}{} ({} of textboxes) ({} of characters)\n{}{:.2}% (median: {}%) (90th percentile: {}%) (99th percentile: {}%)\n{}{} (median: {}) (90th percentile: {}) (99th percentile: {}) (largest: {})",
+            "Number of vertical textboxes: ",
+            self.vertical_box_count,
+            analyzer::get_fancy_percentage(box_count as f64, self.vertical_box_count as f64),
+            analyzer::get_fancy_percentage(char_count as f64, self.vertical_char_count as f64),
+            "Number of horizontal textboxes: ",
+            self.horizontal_box_count,
+            analyzer::get_fancy_percentage(box_count as f64, self.horizontal_box_count as f64),
+            analyzer::get_fancy_percentage(char_count as f64, self.horizontal_char_count as f64),
+            "Page area covered by text: ",
+            self.text_area_ratio * 100.0,
+            self.page_text_area_percentages.median,
+            self.page_text_area_percentages.percentile_90,
+            self.page_text_area_percentages.percentile_99,
+            "Average textbox font size in pixels: ",
+            self.font_sizes.average.floor(),
+            self.font_sizes.median,
+            self.font_sizes.percentile_90,
+            self.font_sizes.percentile_99,
+            self.font_sizes.longest,
+        );
+    }
+}
+
+/// Final numbers of an analysis. Used for both `analysis.txt` and `analysis.json`.
+#[derive(Debug, Serialize)]
+pub struct AnalysisSummary {
+    pub path: String,
+    /// Mokuro title. Only set for `.mokuro` volumes and series.
+    pub title: Option<String>,
+    /// Mokuro volume name. Only set for `.mokuro` volumes.
+    pub volume: Option<String>,
+    pub char_count: u64,
+    pub kanji_count: u64,
+    pub unique_kanji_count: usize,
+    pub kanji_count_single_occurrence: usize,
+    pub word_count: u64,
+    pub unique_word_count: usize,
+    pub word_count_single_occurrence: usize,
+    pub sentence_char_lengths: LengthSummary,
+    pub sentence_word_lengths: LengthSummary,
+    pub box_lengths: LengthSummary,
+    pub page_lengths: LengthSummary,
+    pub volume_lengths: LengthSummary,
+    pub empty_page_count: u64,
+    pub layout: LayoutSummary,
+    /// Textboxes removed by the noise filter by reason.
+    pub filtered_box_counts: HashMap<String, u64>,
+    /// Japanese characters removed by the noise filter by reason.
+    pub filtered_char_counts: HashMap<String, u64>,
+    /// Lines or textboxes skipped for already appearing earlier in the same volume.
+    pub duplicate_line_count: u64,
+    /// Invalid UTF-8 bytes replaced with `U+FFFD` with `--lossy-utf8`.
+    pub invalid_utf8_byte_count: u64,
+    /// Number of characters of each `analyzer::CharClass` by name.
+    pub char_class_counts: HashMap<String, u64>,
+    /// Share of each `analyzer::CharClass` of all characters with a class.
+    pub char_class_ratios: HashMap<String, f64>,
+    pub frequency_bands: Option<Vec<FrequencyBand>>,
+    pub difficulty: DifficultyScore,
+}
+
+impl AnalysisSummary {
+    pub fn format_fancy(&self, parsed_args: &JapaneseTextAnalyzerArgs) -> String {
+        let format_specific_stats = match parsed_args.analysis_type {
+            AnalysisType::MokuroJson | AnalysisType::Mokuro => {
+                format!(
+                    "{}{} ({} total volumes)\n{}\n{}{} ({} total pages)\n{}\n{}{}\n{}{} (shortest: {}) (longest: {}) ({} total textboxes)\n{}",
+                    "Average volume length in characters: ",
+                    self.volume_lengths.average.floor(),
+                    self.volume_lengths.count,
+                    format_length_percentiles(
+                        "Volume length in characters: ",
+                        &self.volume_lengths
+                    ),
+                    "Average page length in characters: ",
+                    self.page_lengths.average.floor(),
+                    self.page_lengths.count,
+                    format_length_percentiles("Page length in characters: ", &self.page_lengths),
+                    "Number of pages with no text: ",
+                    self.empty_page_count,
+                    "Average textbox length in characters: ",
+                    self.box_lengths.average.floor(),
+                    self.box_lengths.shortest,
+                    self.box_lengths.longest,
+                    self.box_lengths.count,
+                    format_length_percentiles("Textbox length in characters: ", &self.box_lengths),
+                ) + "\n"
+                    + &self.layout.format_fancy()
+            }
             AnalysisType::Any => "".to_string(),
-            AnalysisType::Mokuro => format!(
-                "{}{} (shortest: {}) (longest: {}) ({} total textboxes)",
-                "Average textbox length in characters: ",
-                self.avg_box_length,
-                self.shortest_box_length,
-                self.longest_box_length,
-                self.box_count
+        };
+
+        let noise_stats = match parsed_args.noise_filter {
+            Some(_) => {
+                let filtered_box_count: u64 = self.filtered_box_counts.values().sum();
+                let filtered_char_count: u64 = self.filtered_char_counts.values().sum();
+                let reason_counts: Vec<String> = noise_filter::NOISE_REASONS
+                    .iter()
+                    .map(|x| {
+                        format!(
+                            "({}: {})",
+                            x.replace("_", " "),
+                            self.filtered_box_counts.get(*x).unwrap_or(&0)
+                        )
+                    })
+                    .collect();
+                format!(
+                    "{}{} ({} of textboxes) ({} characters) {}",
+                    "Number of textboxes filtered as noise: ",
+                    filtered_box_count,
+                    analyzer::get_fancy_percentage(
+                        (self.box_lengths.count + filtered_box_count) as f64,
+                        filtered_box_count as f64
+                    ),
+                    filtered_char_count,
+                    reason_counts.join(" ")
+                )
+            }
+            None => "".to_string(),
+        };
+
+        let duplicate_stats = match parsed_args.dedupe_lines {
+            true => {
+                let line_name = match parsed_args.analysis_type {
+                    AnalysisType::MokuroJson | AnalysisType::Mokuro => "textboxes",
+                    AnalysisType::Any => "lines",
+                };
+                format!(
+                    "Number of duplicate {} skipped: {}",
+                    line_name, self.duplicate_line_count
+                )
+            }
+            false => "".to_string(),
+        };
+
+        let invalid_utf8_stats = match parsed_args.lossy_utf8 {
+            true => format!(
+                "{}{}",
+                "Number of invalid UTF-8 bytes replaced: ", self.invalid_utf8_byte_count
             ),
+            false => "".to_string(),
         };
 
-        let unique_word_count = self.unique_words.len();
-        let unique_kanji_count = self.unique_kanji.len();
-        let word_count_single_occurrence =
-            analyzer::find_single_occurrences(&self.word_occurrence_list).len();
-        let kanji_count_single_occurrence =
-            analyzer::find_single_occurrences(&self.kanji_occurrence_list).len();
+        let sentence_stats = format!(
+            "{}\n{}",
+            format_length_summary(
+                "Average sentence length in characters: ",
+                &self.sentence_char_lengths,
+                "sentences"
+            ),
+            format_length_summary(
+                "Average sentence length in words: ",
+                &self.sentence_word_lengths,
+                "sentences"
+            ),
+        );
+
+        let frequency_band_stats = match &self.frequency_bands {
+            Some(some) => {
+                let mut band_lines = vec![format!(
+                    "{}{}",
+                    "Reference frequency list: ",
+                    parsed_args.frequency_list.as_deref().unwrap_or_default()
+                )];
+                for frequency_band in some {
+                    band_lines.push(format!(
+                        "Words in frequency band {}: {} unique ({} of unique words) {} total ({} of all words)",
+                        frequency_band.name,
+                        frequency_band.unique_words,
+                        analyzer::get_fancy_percentage(
+                            self.unique_word_count as f64,
+                            frequency_band.unique_words as f64
+                        ),
+                        frequency_band.total_words,
+                        analyzer::get_fancy_percentage(
+                            self.word_count as f64,
+                            frequency_band.total_words as f64
+                        ),
+                    ));
+                }
+                band_lines.join("\n")
+            }
+            None => "".to_string(),
+        };
 
         let formatted_stats = format!(
-            "{}\n{}\n{}{}\n{}{}\n{}{}\n{}{} ({} of unique kanji)\n{}{}\n{}{} ({} of all words)\n{}{} ({} of unique words)\n{}",
-            parsed_args.start_path,
+            "{}\n{}\n{}{}\n{}{}\n{}{}\n{}{} ({} of unique kanji)\n{}{}\n{}{} ({} of all words)\n{}{} ({} of unique words)",
+            self.path,
             "----------------------------------------------------------------------------",
             "Number of Japanese characters: ",
             self.char_count,
             "Number of kanji characters: ",
             self.kanji_count,
             "Number of unique kanji: ",
-            unique_kanji_count,
+            self.unique_kanji_count,
             "Number of unique kanji appearing only once: ",
-            kanji_count_single_occurrence,
+            self.kanji_count_single_occurrence,
             analyzer::get_fancy_percentage(
-                unique_kanji_count as f64,
-                kanji_count_single_occurrence as f64
+                self.unique_kanji_count as f64,
+                self.kanji_count_single_occurrence as f64
             ),
             "Number of words in total: ",
             self.word_count,
             "Number of unique words: ",
-            unique_word_count,
-            analyzer::get_fancy_percentage(self.word_count as f64, unique_word_count as f64),
+            self.unique_word_count,
+            analyzer::get_fancy_percentage(self.word_count as f64, self.unique_word_count as f64),
             "Number of words appearing only once: ",
-            word_count_single_occurrence,
+            self.word_count_single_occurrence,
             analyzer::get_fancy_percentage(
-                unique_word_count as f64,
-                word_count_single_occurrence as f64
+                self.unique_word_count as f64,
+                self.word_count_single_occurrence as f64
             ),
-            format_specific_stats,
         );
 
-        return formatted_stats;
+        let classified_char_count: u64 = self.char_class_counts.values().sum();
+        let char_class_counts: Vec<String> = CharClass::ALL
+            .iter()
+            .map(|x| {
+                let char_class_count = *self.char_class_counts.get(x.get_name()).unwrap_or(&0);
+                format!(
+                    "({}: {} {})",
+                    x.get_name().replace("_", " "),
+                    char_class_count,
+                    analyzer::get_fancy_percentage(
+                        classified_char_count as f64,
+                        char_class_count as f64
+                    )
+                )
+            })
+            .collect();
+        let char_class_stats =
+            format!("{}{}", "Characters by class: ", char_class_counts.join(" "));
+
+        let difficulty_stats = format!(
+            "{}{:.2}",
+            "Difficulty score (0-100): ", self.difficulty.score
+        );
+
+        return [
+            formatted_stats,
+            char_class_stats,
+            format_specific_stats,
+            noise_stats,
+            duplicate_stats,
+            invalid_utf8_stats,
+            sentence_stats,
+            frequency_band_stats,
+            difficulty_stats,
+        ]
+        .into_iter()
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<String>>()
+        .join("\n");
     }
 }
+
+fn format_length_summary(label: &str, length_summary: &LengthSummary, unit_name: &str) -> String {
+    return format!(
+        "{}{} (median: {}) (90th percentile: {}) (99th percentile: {}) (longest: {}) ({} total {})",
+        label,
+        length_summary.average.floor(),
+        length_summary.median,
+        length_summary.percentile_90,
+        length_summary.percentile_99,
+        length_summary.longest,
+        length_summary.count,
+        unit_name
+    );
+}
+
+fn format_length_percentiles(label: &str, length_summary: &LengthSummary) -> String {
+    return format!(
+        "{}(median: {}) (90th percentile: {}) (99th percentile: {}) (longest: {})",
+        label,
+        length_summary.median,
+        length_summary.percentile_90,
+        length_summary.percentile_99,
+        length_summary.longest,
+    );
+}
+
+/// Summarizes each volume, groups volumes with the same Mokuro title into series, and combines everything into the total for `path`.
+///
+/// Returns the combined stats of all volumes along with the report. Skipped files are left for the caller to fill in.
+pub fn get_analysis_report(
+    path: &str,
+    volume_stats: HashMap<String, AnalysisStats>,
+    volume_metadata: &HashMap<String, MokuroMetadata>,
+    frequency_list: Option<&HashMap<String, usize>>,
+    difficulty_weights: DifficultyWeights,
+) -> (AnalysisStats, AnalysisReport) {
+    let mut volume_stats: Vec<(String, AnalysisStats)> = volume_stats.into_iter().collect();
+    volume_stats.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut volume_summaries: Vec<AnalysisSummary> = Default::default();
+    let mut series_stats: HashMap<String, (String, AnalysisStats)> = Default::default();
+    let mut stats = AnalysisStats::default();
+    for (volume_path, volume_stats) in volume_stats {
+        let mut volume_summary =
+            volume_stats.summarize(&volume_path, frequency_list, &difficulty_weights);
+        match volume_metadata.get(&volume_path) {
+            Some(some) => {
+                volume_summary.title = some.title.clone();
+                volume_summary.volume = some.volume.clone();
+                match some.get_series_key() {
+                    Some(series_key) => series_stats.merge_with(
+                        series_key.to_string(),
+                        (some.title.clone().unwrap_or_default(), volume_stats),
+                        |x, y| (x.0, x.1.combine(y.1)),
+                    ),
+                    None => stats = stats.combine(volume_stats),
+                }
+            }
+            None => stats = stats.combine(volume_stats),
+        }
+        volume_summaries.push(volume_summary);
+    }
+
+    let mut series_stats: Vec<(String, AnalysisStats)> = series_stats.into_values().collect();
+    series_stats.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut series_summaries: Vec<AnalysisSummary> = Default::default();
+    for (series_title, series_stats) in series_stats {
+        let mut series_summary =
+            series_stats.summarize(&series_title, frequency_list, &difficulty_weights);
+        series_summary.title = Some(series_title);
+        series_summaries.push(series_summary);
+        stats = stats.combine(series_stats);
+    }
+    let summary = stats.summarize(path, frequency_list, &difficulty_weights);
+
+    let analysis_report = AnalysisReport {
+        difficulty_weights: difficulty_weights,
+        total: summary,
+        series: series_summaries,
+        volumes: volume_summaries,
+        non_mokuro_files: vec![],
+        duplicate_volumes: vec![],
+        duplicate_files: vec![],
+    };
+    return (stats, analysis_report);
+}
+
+/// Contents of `analysis.json`.
+#[derive(Debug, Serialize)]
+pub struct AnalysisReport {
+    pub difficulty_weights: DifficultyWeights,
+    pub total: AnalysisSummary,
+    /// Volumes grouped by Mokuro title. Only available for `.mokuro` files.
+    pub series: Vec<AnalysisSummary>,
+    pub volumes: Vec<AnalysisSummary>,
+    /// Json files skipped for not having the layout of Mokuro output.
+    pub non_mokuro_files: Vec<String>,
+    /// `.mokuro` files skipped for having the same `volume_uuid` as another file.
+    pub duplicate_volumes: Vec<DuplicateFile>,
+    /// Files skipped for having the same contents as another file. Only checked with `--dedupe-files`.
+    pub duplicate_files: Vec<DuplicateFile>,
+}
+
+/// A file skipped for being a duplicate of another file.
+#[derive(Debug, Serialize)]
+pub struct DuplicateFile {
+    pub path: String,
+    pub duplicate_of: String,
+}
@@ -31,7 +31,13 @@ pub fn parse_minimal_synthetic_json() {
     dbg!("{:?}", &json_files);
     assert!(json_files.len() == 1);
 
-    let lines = crate::file_handler::get_json_file_data(json_files.get(0).unwrap());
+    let lines = crate::file_handler::get_json_file_data(
+        json_files.get(0).unwrap(),
+        &Default::default(),
+        None,
+    )
+    .unwrap()
+    .lines;
     assert!(vec![lines.clone()] == EXPECTED_LINES);
 
     //tokenize text
@@ -39,8 +45,17 @@ pub fn parse_minimal_synthetic_json() {
         crate::dict_handler::make_sudachi_dict().expect("Failed to load tokenizer dictionary");
     let tokenizer = sudachi::analysis::stateless_tokenizer::StatelessTokenizer::new(&dict);
     let mut tokenized_data = vec![];
-    tokenized_data.append(&mut crate::run_tokenization(&lines, &tokenizer));
-    assert!(tokenized_data == EXPECTED_TOKENIZED_DATA);
+    tokenized_data.append(
+        &mut crate::text_analyzer::run_tokenization(&lines, &tokenizer, "")
+            .0
+            .concat(),
+    );
+    assert!(
+        tokenized_data
+            .iter()
+            .map(|x| x.as_str())
+            .eq(EXPECTED_TOKENIZED_DATA)
+    );
 }
 
 #[test]
@@ -55,7 +70,7 @@ pub fn parse_minimal_synthetic_any() {
             .unwrap();
     let mut all_lines = vec![];
     for lines in lines_groupings {
-        all_lines.push(lines);
+        all_lines.push(lines.unwrap().lines);
     }
     assert!(all_lines == EXPECTED_LINES);
 
@@ -68,8 +83,754 @@ pub fn parse_minimal_synthetic_any() {
     let tokenizer = sudachi::analysis::stateless_tokenizer::StatelessTokenizer::new(&dict);
     let mut tokenized_data = vec![];
     for lines in lines_groupings {
-        tokenized_data.append(&mut crate::run_tokenization(&lines, &tokenizer));
+        let lines = lines.unwrap().lines;
+        tokenized_data.append(
+            &mut crate::text_analyzer::run_tokenization(&lines, &tokenizer, "")
+                .0
+                .concat(),
+        );
     }
     dbg!(&tokenized_data);
-    assert!(tokenized_data == EXPECTED_TOKENIZED_DATA);
+    assert!(
+        tokenized_data
+            .iter()
+            .map(|x| x.as_str())
+            .eq(EXPECTED_TOKENIZED_DATA)
+    );
+}
+
+#[test]
+pub fn split_sentences() {
+    use crate::interner::Word;
+    let line =
+        "「行こう。早く！」「うん……」そう言って彼は走った！？　「雨」だと思った……晴れだった。";
+    let sentences: Vec<String> = crate::analyzer::split_sentences(line)
+        .into_iter()
+        .map(|x| line.chars().collect::<Vec<char>>()[x].iter().collect())
+        .collect();
+    assert!(
+        sentences
+            == vec![
+                "「行こう。早く！」",
+                "「うん……」",
+                "そう言って彼は走った！？",
+                "「雨」だと思った……晴れだった。",
+            ]
+    );
+
+    let morpheme_surfaces: Vec<Word> = vec!["雨", "だっ", "た", "。", "　", "晴れ", "！"]
+        .into_iter()
+        .map(Word::new)
+        .collect();
+    let sentence_lengths =
+        crate::analyzer::get_sentence_lengths("雨だった。　晴れ！", &morpheme_surfaces);
+    assert!(sentence_lengths == vec![(5, 3), (3, 1)]);
+}
+
+//Two volumes of three pages with one character per morpheme:
+//volume_a: page 1 has textboxes of 2 and 3 characters, page 2 a textbox of 4 characters
+//volume_b: page 3 has textboxes of 1 and 6 characters
+#[cfg(test)]
+const SAMPLE_PAGES: [(&str, &[&str]); 3] = [
+    ("./sample_manga/volume_a", &["あい", "うえお"]),
+    ("./sample_manga/volume_a", &["かきくけ"]),
+    ("./sample_manga/volume_b", &["さ", "しすせそたち"]),
+];
+
+#[cfg(test)]
+fn get_sample_page_stats() -> Vec<crate::stats_handler::AnalysisStats> {
+    return SAMPLE_PAGES
+        .iter()
+        .map(|(volume_path, textboxes)| {
+            let lines: Vec<String> = textboxes.iter().map(|x| x.to_string()).collect();
+            let morpheme_surfaces: Vec<Vec<crate::interner::Word>> = lines
+                .iter()
+                .map(|x| {
+                    x.chars()
+                        .map(|x| crate::interner::Word::new(&x.to_string()))
+                        .collect()
+                })
+                .collect();
+            let text_source = crate::stats_handler::TextSource {
+                volume_path: volume_path.to_string(),
+                is_page: true,
+            };
+            crate::stats_handler::get_stats(lines.into(), morpheme_surfaces, &text_source)
+        })
+        .collect();
+}
+
+#[test]
+pub fn sample_averages() {
+    let parsed_args = crate::args_parser::get_args(vec![
+        "japanese_text_analyzer".to_string(),
+        "./sample_manga/".to_string(),
+    ]);
+    let difficulty_weights = crate::difficulty_handler::DifficultyWeights::default();
+
+    //combine every page in order
+    let stats = get_sample_page_stats()
+        .into_iter()
+        .fold(crate::stats_handler::AnalysisStats::default(), |x, y| {
+            x.combine(y)
+        });
+    let summary = stats.summarize(&parsed_args.start_path, None, &difficulty_weights);
+    //2 + 3 + 4 + 1 + 6 characters
+    assert!(summary.char_count == 16);
+    //volume_a has 9 characters and volume_b 7
+    assert!(summary.volume_lengths.count == 2);
+    assert!(summary.volume_lengths.average == 8.0);
+    //pages of 5, 4, and 7 characters
+    assert!(summary.page_lengths.count == 3);
+    assert!(summary.page_lengths.average == 16.0 / 3.0);
+    assert!(summary.page_lengths.median == 5);
+    //textboxes of 1, 2, 3, 4, and 6 characters
+    assert!(summary.box_lengths.count == 5);
+    assert!(summary.box_lengths.average == 3.2);
+    assert!(summary.box_lengths.median == 3);
+    assert!(summary.box_lengths.shortest == 1);
+    assert!(summary.box_lengths.longest == 6);
+
+    //averages are printed rounded down
+    let formatted_stats = summary.format_fancy(&parsed_args);
+    for expected_line in [
+        "Number of Japanese characters: 16",
+        "Average volume length in characters: 8 (2 total volumes)",
+        "Average page length in characters: 5 (3 total pages)",
+        "Average textbox length in characters: 3 (shortest: 1) (longest: 6) (5 total textboxes)",
+    ] {
+        assert!(formatted_stats.lines().any(|x| x == expected_line));
+    }
+
+    //combine pages in reverse into 2 groups then combine the groups
+    let mut group_stats: Vec<crate::stats_handler::AnalysisStats> = vec![];
+    for (i, page_stats) in get_sample_page_stats().into_iter().rev().enumerate() {
+        match group_stats.get_mut(i % 2) {
+            Some(some) => *some = std::mem::take(some).combine(page_stats),
+            None => group_stats.push(page_stats),
+        }
+    }
+    let regrouped_stats = group_stats
+        .into_iter()
+        .fold(crate::stats_handler::AnalysisStats::default(), |x, y| {
+            x.combine(y)
+        });
+    let regrouped_formatted_stats = regrouped_stats
+        .summarize(&parsed_args.start_path, None, &difficulty_weights)
+        .format_fancy(&parsed_args);
+    assert!(formatted_stats == regrouped_formatted_stats);
+}
+
+#[test]
+pub fn parse_minimal_synthetic_mokuro() {
+    let mokuro_files =
+        crate::file_handler::get_files("./src/tests/data/minimal_synthetic.mokuro", ".mokuro");
+    assert!(mokuro_files.len() == 1);
+
+    let pages = crate::file_handler::get_mokuro_file_data(
+        mokuro_files.get(0).unwrap(),
+        &Default::default(),
+        None,
+    )
+    .unwrap();
+    assert!(pages.len() == 3);
+    assert!(pages[0].lines == EXPECTED_LINES[0][0..2]);
+    assert!(pages[1].lines.is_empty());
+    assert!(pages[2].lines == EXPECTED_LINES[0][2..4]);
+    assert!(pages[0].layout.vertical_box_count == 2);
+    assert!(pages[2].layout.horizontal_box_count == 1);
+    assert!(
+        pages[2].layout.font_size_box_counts == std::collections::HashMap::from([(24, 1), (50, 1)])
+    );
+    assert!(
+        pages[2].layout.page_text_area_percentages == std::collections::HashMap::from([(2, 1)])
+    );
+
+    let metadata = crate::file_handler::get_mokuro_metadata(mokuro_files.get(0).unwrap()).unwrap();
+    assert!(metadata.title.as_deref() == Some("minimal_synthetic"));
+    assert!(metadata.volume.as_deref() == Some("minimal_synthetic_01"));
+    assert!(metadata.get_series_key() == Some("6f1d0c5e-3a51-4c8e-9a57-2f4be0a3c001"));
+}
+
+#[test]
+pub fn filter_noise() {
+    let noise_filter = crate::noise_filter::parse_noise_filter("").unwrap();
+    assert!(noise_filter.get_noise_reason("ドドドッ", 30.0, 1000.0) == Some("repeated_kana"));
+    assert!(noise_filter.get_noise_reason("ざわざわざわ", 30.0, 1000.0) == Some("repeated_kana"));
+    assert!(noise_filter.get_noise_reason("ガ", 30.0, 1000.0) == Some("repeated_kana"));
+    assert!(noise_filter.get_noise_reason("|||A", 30.0, 1000.0) == Some("japanese_ratio"));
+    assert!(noise_filter.get_noise_reason("いやいや", 30.0, 1000.0) == None);
+    assert!(noise_filter.get_noise_reason("え？", 30.0, 1000.0) == None);
+
+    let mokuro_files =
+        crate::file_handler::get_files("./src/tests/data/minimal_synthetic.mokuro", ".mokuro");
+    let noise_filter = crate::noise_filter::parse_noise_filter("max_font:40").unwrap();
+    let pages = crate::file_handler::get_mokuro_file_data(
+        mokuro_files.get(0).unwrap(),
+        &noise_filter,
+        None,
+    )
+    .unwrap();
+    assert!(pages[2].lines == EXPECTED_LINES[0][2..3]);
+    assert!(
+        pages[2].noise.box_counts
+            == std::collections::HashMap::from([("font_size".to_string(), 1)])
+    );
+    assert!(
+        pages[2].noise.char_counts
+            == std::collections::HashMap::from([("font_size".to_string(), 8)])
+    );
+}
+
+#[test]
+pub fn dedupe_lines() {
+    let line_deduplicator = crate::file_handler::LineDeduplicator::default();
+    let lines = vec!["第一話".to_string(), "".to_string(), "".to_string()];
+    let text_chunk = line_deduplicator.remove_duplicates(
+        "volume_1",
+        crate::file_handler::TextChunk::from(
+            [
+                lines.clone(),
+                vec!["第一話".to_string(), "おはよう".to_string()],
+            ]
+            .concat(),
+        ),
+    );
+    assert!(text_chunk.lines == ["第一話", "", "", "おはよう"]);
+    assert!(text_chunk.duplicate_line_count == 1);
+
+    let text_chunk = line_deduplicator.remove_duplicates(
+        "volume_2",
+        crate::file_handler::TextChunk::from(lines.clone()),
+    );
+    assert!(text_chunk.lines == lines);
+    assert!(text_chunk.duplicate_line_count == 0);
+}
+
+#[test]
+pub fn read_invalid_utf8() {
+    let mut lines_groupings = crate::file_handler::BufferedPlainLineReader::new(
+        &std::path::PathBuf::from("./src/tests/data/invalid_utf8.txt"),
+    )
+    .unwrap();
+    let text_chunk = lines_groupings.next().unwrap().unwrap();
+    assert!(text_chunk.lines == ["吾輩は猫である", ""]);
+    assert!(text_chunk.byte_count == 22);
+    match lines_groupings.next() {
+        Some(Err(crate::error::AnalyzerError::Io { source, .. })) => {
+            assert!(source.kind() == std::io::ErrorKind::InvalidData);
+            assert!(source.to_string() == "Invalid UTF-8 at byte 22");
+        }
+        _ => panic!("Invalid UTF-8 was not reported"),
+    }
+    assert!(lines_groupings.next().is_none());
+}
+
+#[test]
+pub fn strict_analysis_stops_at_invalid_utf8() {
+    use crate::{Analyzer, AnalyzerOptions, args_parser::AnalysisType};
+    let dict =
+        crate::dict_handler::make_sudachi_dict().expect("Failed to load tokenizer dictionary");
+    let filepaths = vec![std::path::PathBuf::from(
+        "./src/tests/data/invalid_utf8.txt",
+    )];
+
+    let strict_analyzer = Analyzer::new(
+        &dict,
+        AnalyzerOptions {
+            analysis_type: AnalysisType::Any,
+            strict: true,
+            ..Default::default()
+        },
+    );
+    match strict_analyzer.analyze_files(&filepaths) {
+        Err(err) => assert!(err.to_problem().kind == "invalid_utf8"),
+        Ok(_) => panic!("Strict analysis did not stop at invalid UTF-8"),
+    }
+
+    let lenient_analyzer = Analyzer::new(
+        &dict,
+        AnalyzerOptions {
+            analysis_type: AnalysisType::Any,
+            ..Default::default()
+        },
+    );
+    assert!(lenient_analyzer.analyze_files(&filepaths).is_ok());
+    let errors = lenient_analyzer.take_errors();
+    assert!(errors.len() == 1);
+    assert!(errors[0].to_problem().kind == "invalid_utf8");
+}
+
+#[test]
+pub fn strict_analysis_lists_non_mokuro_files() {
+    use crate::{Analyzer, AnalyzerOptions, args_parser::AnalysisType};
+    let dict =
+        crate::dict_handler::make_sudachi_dict().expect("Failed to load tokenizer dictionary");
+    let filepaths = vec![std::path::PathBuf::from("./src/tests/data/not_mokuro.json")];
+
+    let strict_analyzer = Analyzer::new(
+        &dict,
+        AnalyzerOptions {
+            analysis_type: AnalysisType::MokuroJson,
+            strict: true,
+            ..Default::default()
+        },
+    );
+    let volume_stats = strict_analyzer.analyze_files(&filepaths).unwrap();
+    assert!(volume_stats.is_empty());
+    let errors = strict_analyzer.take_errors();
+    assert!(errors.len() == 1);
+    assert!(errors[0].is_ignored_input() && errors[0].to_problem().kind == "not_mokuro");
+}
+
+#[test]
+pub fn read_invalid_utf8_lossy() {
+    let filepath = std::path::PathBuf::from("./src/tests/data/invalid_utf8.txt");
+    let mut lines_groupings = crate::file_handler::BufferedPlainLineReader::new(&filepath).unwrap();
+    lines_groupings.read_lossy(true);
+    let text_chunk = lines_groupings.next().unwrap().unwrap();
+    assert!(text_chunk.lines == ["吾輩は猫である", "\u{FFFD}\u{FFFD}", "名前", ""]);
+    assert!(text_chunk.byte_count == 32);
+    assert!(text_chunk.invalid_utf8_byte_count == 2);
+    assert!(lines_groupings.next().is_none());
+
+    //Characters cut off at the end of the buffer are read with the next chunk
+    let mut bufreader = crate::utf8_bufreader::Utf8BufReader::new(&filepath, 5).unwrap();
+    bufreader.read_lossy(true);
+    let decoded_texts: Vec<crate::utf8_bufreader::DecodedText> =
+        bufreader.map(|x| x.unwrap()).collect();
+    assert!(
+        decoded_texts
+            .iter()
+            .map(|x| x.text.as_ref())
+            .collect::<String>()
+            == "吾輩は猫である\n\u{FFFD}\u{FFFD}\n名前\n"
+    );
+    assert!(
+        decoded_texts
+            .iter()
+            .map(|x| x.replaced_byte_count)
+            .sum::<u64>()
+            == 2
+    );
+}
+
+#[test]
+pub fn read_chunks_at_line_breaks() {
+    let test_dir = std::env::temp_dir().join(format!(
+        "japanese_text_analyzer_chunks_{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&test_dir).unwrap();
+    let sentences = [
+        "吾輩は猫である。",
+        "名前はまだ無い。",
+        "どこで生れたかとんと見当がつかぬ。",
+    ];
+    let texts = [
+        (0..1000)
+            .map(|x| sentences[x % sentences.len()])
+            .collect::<Vec<&str>>()
+            .join("\n"),
+        //Text without line breaks is split at sentences
+        sentences.concat().repeat(300),
+    ];
+    for (index, text) in texts.iter().enumerate() {
+        let filepath = test_dir.join(format!("{}.txt", index));
+        std::fs::write(&filepath, text).unwrap();
+        let text_chunks: Vec<crate::file_handler::TextChunk> =
+            crate::file_handler::BufferedPlainLineReader::with_chunk_size(&filepath, 4096)
+                .unwrap()
+                .map(|x| x.unwrap())
+                .collect();
+        assert!(text_chunks.len() > 1);
+        assert!(text_chunks.iter().map(|x| x.byte_count).sum::<u64>() == text.len() as u64);
+        let lines: Vec<String> = text_chunks.into_iter().flat_map(|x| x.lines).collect();
+        match index {
+            0 => assert!(lines == crate::file_handler::split_plain_text(text)),
+            _ => assert!(lines.concat() == *text),
+        }
+    }
+    let _ = std::fs::remove_dir_all(test_dir);
+}
+
+#[test]
+pub fn chunk_size_does_not_change_cr_line_stats() {
+    use crate::{Analyzer, AnalyzerOptions, args_parser::AnalysisType};
+    let filepath = std::env::temp_dir().join(format!(
+        "japanese_text_analyzer_cr_lines_{}.txt",
+        std::process::id()
+    ));
+    //Sentences ending inside the lines are where chunks without line breaks would be cut
+    let sentences = [
+        "吾輩は猫である。名前はまだ無い",
+        "どこで生れたか",
+        "とんと見当がつかぬ。そうだ",
+    ];
+    let text: String = (0..2000)
+        .map(|x| sentences[x % sentences.len()].to_string() + "\r")
+        .collect();
+    std::fs::write(&filepath, text).unwrap();
+    let dict =
+        crate::dict_handler::make_sudachi_dict().expect("Failed to load tokenizer dictionary");
+
+    let stats: Vec<crate::stats_handler::AnalysisStats> = [4096, 10_000]
+        .into_iter()
+        .map(|chunk_size| {
+            Analyzer::new(
+                &dict,
+                AnalyzerOptions {
+                    analysis_type: AnalysisType::Any,
+                    chunk_size: Some(chunk_size),
+                    ..Default::default()
+                },
+            )
+            .analyze_file(&filepath)
+            .unwrap()
+        })
+        .collect();
+    let _ = std::fs::remove_file(&filepath);
+    assert!(stats[0].box_lengths == stats[1].box_lengths);
+    assert!(stats[0].box_lengths.values().sum::<u64>() == 2000);
+    assert!(stats[0].char_count == stats[1].char_count);
+    assert!(stats[0].sentence_char_lengths == stats[1].sentence_char_lengths);
+}
+
+#[test]
+pub fn detect_mokuro_format() {
+    use crate::{
+        error::AnalyzerError,
+        file_handler::{get_json_file_data, get_mokuro_file_data},
+        noise_filter::NoiseFilter,
+    };
+    let page_json = std::path::PathBuf::from("./src/tests/data/minimal_synthetic.json");
+    let volume_file = std::path::PathBuf::from("./src/tests/data/minimal_synthetic.mokuro");
+    let not_mokuro = std::path::PathBuf::from("./src/tests/data/not_mokuro.json");
+    let not_json = std::path::PathBuf::from("./src/tests/data/minimal_synthetic.txt");
+    let noise_filter = NoiseFilter::default();
+
+    assert!(get_json_file_data(&page_json, &noise_filter, None).is_ok());
+    assert!(matches!(
+        get_mokuro_file_data(&page_json, &noise_filter, None),
+        Err(AnalyzerError::NotMokuro { .. })
+    ));
+    assert!(get_mokuro_file_data(&volume_file, &noise_filter, None).is_ok());
+    let metadata = crate::file_handler::get_mokuro_metadata(&volume_file).unwrap();
+    assert!(metadata.version.as_deref() == Some("0.2.1"));
+    let err = get_json_file_data(&not_mokuro, &noise_filter, None).unwrap_err();
+    assert!(err.is_skipped_input() && err.to_problem().kind == "not_mokuro");
+    assert!(matches!(
+        get_json_file_data(&not_json, &noise_filter, None),
+        Err(AnalyzerError::Json { .. })
+    ));
+}
+
+#[test]
+pub fn stats_cache_round_trip() {
+    use crate::interner::Word;
+    use crate::stats_cache::{CacheLookup, StatsCache, StatsCacheEntry};
+    use std::collections::HashMap;
+    let cache_dir = std::env::temp_dir().join(format!(
+        "japanese_text_analyzer_stats_cache_{}",
+        std::process::id()
+    ));
+    let stats_cache = StatsCache::new(cache_dir.clone(), "test");
+    let filepath = std::path::PathBuf::from("./src/tests/data/minimal_synthetic.txt");
+
+    let text_source = crate::stats_handler::TextSource {
+        volume_path: "volume".to_string(),
+        is_page: false,
+    };
+    let stats = crate::stats_handler::get_stats(
+        vec!["吾輩は猫".to_string()].into(),
+        vec![vec![Word::new("吾輩"), Word::new("は"), Word::new("猫")]],
+        &text_source,
+    );
+    let file_key = match stats_cache.lookup(&filepath) {
+        CacheLookup::Miss(some) => some,
+        _ => panic!("Empty stats cache did not miss"),
+    };
+    let word_list_raw_file = stats_cache.create_word_list_raw_file(&file_key).unwrap();
+    word_list_raw_file.write("吾輩\nは\n猫\n");
+    let mut entry = StatsCacheEntry::new(file_key, stats, None);
+    stats_cache
+        .store_with_word_list_raw(&mut entry, word_list_raw_file)
+        .unwrap();
+
+    match stats_cache.lookup(&filepath) {
+        CacheLookup::Hit(entry) => {
+            assert!(entry.stats.char_count == 4);
+            assert!(entry.stats.kanji_count == 3);
+            assert!(entry.stats.word_count == 3);
+            assert!(entry.stats.box_lengths == HashMap::from([(4, 1)]));
+            assert!(entry.stats.volume_lengths == HashMap::from([("volume".to_string(), 4)]));
+            assert!(
+                entry.stats.kanji_occurrence_list
+                    == HashMap::from([('吾', 1), ('輩', 1), ('猫', 1)])
+            );
+            assert!(
+                entry.stats.word_occurrence_list
+                    == HashMap::from([
+                        (Word::new("吾輩"), 1),
+                        (Word::new("は"), 1),
+                        (Word::new("猫"), 1)
+                    ])
+            );
+            let mut word_list_raw = String::new();
+            std::io::Read::read_to_string(
+                &mut stats_cache.open_word_list_raw(&entry).unwrap(),
+                &mut word_list_raw,
+            )
+            .unwrap();
+            assert!(word_list_raw == "吾輩\nは\n猫\n");
+        }
+        _ => panic!("Stored stats were not found"),
+    }
+    let _ = std::fs::remove_dir_all(cache_dir);
+}
+
+#[test]
+pub fn normalize_plain_text() {
+    let lines = crate::file_handler::split_plain_text("\u{FEFF}吾輩は\r\n猫である\r名前は\n");
+    assert!(lines == ["吾輩は", "猫である", "名前は", ""]);
+
+    let text = "ｶﾀｶﾅとＡＢＣ";
+    let normalize = |normalization| crate::file_handler::normalize_text(text, normalization);
+    assert!(normalize(crate::file_handler::TextNormalization::Nfkc) == "カタカナとABC");
+    assert!(normalize(crate::file_handler::TextNormalization::Nfc) == text);
+    assert!(normalize(crate::file_handler::TextNormalization::None) == text);
+}
+
+#[test]
+pub fn char_classes() {
+    use crate::{analyzer::CharClass, interner::Word};
+    let expected_char_classes = [
+        ('あ', Some(CharClass::Hiragana)),
+        ('ア', Some(CharClass::Katakana)),
+        ('ｱ', Some(CharClass::HalfWidthKatakana)),
+        ('猫', Some(CharClass::Kanji)),
+        ('\u{1B002}', Some(CharClass::ExtendedKana)),
+        ('。', Some(CharClass::JapanesePunctuation)),
+        ('ー', Some(CharClass::JapanesePunctuation)),
+        ('！', Some(CharClass::JapanesePunctuation)),
+        ('Ａ', Some(CharClass::FullWidthAlphanumeric)),
+        ('a', Some(CharClass::Latin)),
+        ('é', Some(CharClass::Latin)),
+        (' ', None),
+        ('\u{3000}', None),
+    ];
+    for (character, char_class) in expected_char_classes {
+        assert!(crate::analyzer::get_char_class(character as u32) == char_class);
+        assert!(
+            crate::analyzer::check_if_japanese(character as u32)
+                == char_class.is_some_and(|x| x.is_japanese())
+        );
+    }
+
+    let lines = vec!["ネコと猫。".to_string()];
+    let morpheme_surfaces = vec![vec![
+        Word::new("ネコ"),
+        Word::new("と"),
+        Word::new("猫"),
+        Word::new("。"),
+    ]];
+    let text_source = crate::stats_handler::TextSource {
+        volume_path: "".to_string(),
+        is_page: false,
+    };
+    let stats = crate::stats_handler::get_stats(lines.into(), morpheme_surfaces, &text_source);
+    assert!(stats.char_class_counts[CharClass::Katakana as usize] == 2);
+    assert!(stats.char_class_counts[CharClass::Hiragana as usize] == 1);
+    assert!(stats.char_class_counts[CharClass::Kanji as usize] == 1);
+    assert!(stats.char_class_counts[CharClass::JapanesePunctuation as usize] == 1);
+    assert!(stats.char_class_counts[CharClass::Latin as usize] == 0);
+    let summary = stats.summarize("", None, &Default::default());
+    assert!(summary.char_class_counts["katakana"] == 2);
+    assert!(summary.char_class_ratios["latin"] == 0.0);
+}
+
+#[test]
+pub fn duplicate_files_need_the_same_contents() {
+    use crate::file_handler::{check_if_same_contents, remove_duplicates_by_key};
+    use std::path::PathBuf;
+    let directory = std::env::temp_dir().join(format!(
+        "japanese_text_analyzer_test_duplicates_{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&directory).unwrap();
+    let filepath = directory.join("a.txt");
+    let same_filepath = directory.join("b.txt");
+    let other_filepath = directory.join("c.txt");
+    std::fs::write(&filepath, "吾輩は猫である").unwrap();
+    std::fs::write(&same_filepath, "吾輩は猫である").unwrap();
+    std::fs::write(&other_filepath, "吾輩は犬である").unwrap();
+
+    //Every file has the same key, as if their hashes collided
+    let (kept, duplicates) = remove_duplicates_by_key(
+        vec![
+            other_filepath.clone(),
+            same_filepath.clone(),
+            filepath.clone(),
+        ],
+        |_: &PathBuf| Some(0),
+        check_if_same_contents,
+    );
+    let _ = std::fs::remove_dir_all(&directory);
+    assert!(kept == [filepath.clone(), other_filepath]);
+    assert!(duplicates == [(same_filepath, filepath)]);
+}
+
+#[test]
+pub fn frequency_bands() {
+    use crate::frequency_handler::{get_frequency_bands, get_vocabulary_rarity};
+    use crate::interner::Word;
+    use std::collections::HashMap;
+    let frequency_list: HashMap<String, usize> = [
+        ("一", 1),
+        ("二", 1_000),
+        ("三", 1_001),
+        ("四", 2_000),
+        ("五", 2_001),
+        ("六", 5_000),
+        ("七", 5_001),
+        ("八", 10_000),
+        ("九", 10_001),
+    ]
+    .into_iter()
+    .map(|x| (x.0.to_string(), x.1))
+    .collect();
+    let mut word_occurrence_list: HashMap<Word, u64> =
+        frequency_list.keys().map(|x| (Word::new(x), 1)).collect();
+    word_occurrence_list.insert(Word::new("十"), 3);
+
+    let frequency_bands = get_frequency_bands(&word_occurrence_list, &frequency_list);
+    let bands: Vec<(&str, u64, u64)> = frequency_bands
+        .iter()
+        .map(|x| (x.name, x.unique_words, x.total_words))
+        .collect();
+    assert!(
+        bands
+            == [
+                ("top 1k", 2, 2),
+                ("1k-2k", 2, 2),
+                ("2k-5k", 2, 2),
+                ("5k-10k", 2, 2),
+                ("10k+", 1, 1),
+                ("not listed", 1, 3),
+            ]
+    );
+    //5k-10k, 10k+, and not listed: 2 + 1 + 3 of 12 words
+    assert!(get_vocabulary_rarity(&frequency_bands) == 0.5);
+    assert!(get_vocabulary_rarity(&get_frequency_bands(&HashMap::new(), &frequency_list)) == 0.0);
+}
+
+#[test]
+pub fn load_frequency_list() {
+    let filepath = std::env::temp_dir().join(format!(
+        "japanese_text_analyzer_frequency_list_{}.csv",
+        std::process::id()
+    ));
+    std::fs::write(&filepath, "の\t100\n\nに\t50\nの\t10\n猫\n").unwrap();
+    let frequency_list =
+        crate::frequency_handler::load_frequency_list(&filepath.to_string_lossy()).unwrap();
+    let _ = std::fs::remove_file(&filepath);
+    //Empty lines don't take a rank and repeated words keep their first rank
+    assert!(frequency_list.len() == 3);
+    assert!(frequency_list["の"] == 1);
+    assert!(frequency_list["に"] == 2);
+    assert!(frequency_list["猫"] == 4);
+}
+
+#[test]
+pub fn difficulty_score() {
+    use crate::difficulty_handler::{
+        DifficultyMetrics, DifficultyWeights, get_difficulty_score, parse_difficulty_weights,
+    };
+    let get_metrics = || DifficultyMetrics {
+        avg_box_length: 80.0,
+        kanji_ratio: 0.25,
+        unique_word_ratio: 0.5,
+        single_occurrence_ratio: 1.5,
+        vocabulary_rarity: None,
+    };
+
+    //Metrics past their ceilings count as 1
+    let difficulty_score = get_difficulty_score(get_metrics(), &DifficultyWeights::default());
+    assert!(difficulty_score.textbox_length == 1.0);
+    assert!(difficulty_score.kanji_ratio == 0.5);
+    assert!(difficulty_score.unique_word_ratio == 0.5);
+    assert!(difficulty_score.single_occurrence_ratio == 1.0);
+    assert!(difficulty_score.vocabulary_rarity.is_none());
+    assert!(difficulty_score.score == 75.0);
+
+    let difficulty_score = get_difficulty_score(
+        DifficultyMetrics {
+            vocabulary_rarity: Some(1.0),
+            ..get_metrics()
+        },
+        &parse_difficulty_weights("rarity:4").unwrap(),
+    );
+    assert!(difficulty_score.score == 87.5);
+
+    //Negative and non-finite metrics count as 0
+    let difficulty_score = get_difficulty_score(
+        DifficultyMetrics {
+            avg_box_length: f64::NAN,
+            vocabulary_rarity: Some(-0.5),
+            ..get_metrics()
+        },
+        &DifficultyWeights::default(),
+    );
+    assert!(difficulty_score.textbox_length == 0.0);
+    assert!(difficulty_score.vocabulary_rarity == Some(0.0));
+
+    //Metrics with a weight of 0 are left out of the average
+    let difficulty_score = get_difficulty_score(
+        get_metrics(),
+        &parse_difficulty_weights("textbox:0,single:0").unwrap(),
+    );
+    assert!(difficulty_score.score == 50.0);
+    let difficulty_score = get_difficulty_score(
+        get_metrics(),
+        &parse_difficulty_weights("textbox:0,kanji:0,unique:0,single:0,rarity:0").unwrap(),
+    );
+    assert!(difficulty_score.score == 0.0);
+}
+
+#[test]
+pub fn parse_difficulty_weights() {
+    use crate::difficulty_handler::parse_difficulty_weights;
+    let weights = parse_difficulty_weights("kanji:2,rarity:0.5,").unwrap();
+    assert!(weights.kanji_ratio == 2.0);
+    assert!(weights.vocabulary_rarity == 0.5);
+    assert!(weights.textbox_length == 1.0);
+
+    for invalid_weights in [
+        "kanji",
+        "kanji:",
+        "kanji:abc",
+        "kanji:-1",
+        "kanji:inf",
+        "kanji:NaN",
+        "size:1",
+        "kanji:1;unique:1",
+    ] {
+        assert!(parse_difficulty_weights(invalid_weights).is_err());
+    }
+}
+
+#[test]
+pub fn adaptive_chunk_size() {
+    use crate::file_handler::get_adaptive_chunk_size;
+    //Tiny files are read in chunks of at least 64 KB
+    assert!(get_adaptive_chunk_size(0, 1) == 65_536);
+    assert!(get_adaptive_chunk_size(1_000, 8) == 65_536);
+    assert!(get_adaptive_chunk_size(262_140, 1) == 65_536);
+    //Huge files are read in chunks of at most 10 MB
+    assert!(get_adaptive_chunk_size(100_000_000_000, 1) == 10_000_000);
+    assert!(get_adaptive_chunk_size(100_000_000_000, 64) == 10_000_000);
+    //Four chunks for each thread in between
+    assert!(get_adaptive_chunk_size(4_000_000, 1) == 1_000_000);
+    assert!(get_adaptive_chunk_size(8_000_000, 2) == 1_000_000);
+    assert!(get_adaptive_chunk_size(8_000_000, 8) == 250_000);
 }
@@ -0,0 +1,713 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{BufRead, Write},
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+};
+
+use rayon::iter::{
+    IntoParallelIterator, IntoParallelRefIterator, ParallelBridge, ParallelIterator,
+};
+use sudachi::{
+    analysis::stateless_tokenizer::StatelessTokenizer, dic::dictionary::JapaneseDictionary,
+};
+
+use crate::{
+    analyzer,
+    args_parser::AnalysisType,
+    dict_handler,
+    error::AnalyzerError,
+    file_handler::{self, LineDeduplicator, TextChunk, TextNormalization},
+    interner::Word,
+    noise_filter::NoiseFilter,
+    progress_handler::Progress,
+    stats_cache::{CacheLookup, StatsCache, StatsCacheEntry, WordListRawCacheFile},
+    stats_handler::{self, AnalysisStats, TextSource},
+    type_extensions::HashMapExtensions,
+    word_list_raw_writer::{WordListRawWriter, format_word_list_raw},
+};
+
+/// Settings for an `Analyzer`. The defaults match running the CLI without any options.
+#[derive(Default)]
+pub struct AnalyzerOptions {
+    /// How files passed to `analyze_file` and `analyze_files` are read.
+    pub analysis_type: AnalysisType,
+    pub singlethreaded: bool,
+    /// Size in bytes of the chunks plain text is read and analyzed in. Raised to at least `file_handler::MIN_PLAIN_BUFFER_SIZE`.
+    ///
+    /// When not set, plain text files are split so even a single file is spread over every thread of the current rayon pool.
+    pub chunk_size: Option<usize>,
+    pub noise_filter: NoiseFilter,
+    /// Skip lines or textboxes which already appeared earlier in the same volume.
+    ///
+    /// The pages of a volume of `AnalysisType::MokuroJson` are then analyzed one after another in path order so the same textboxes are kept with any number of threads.
+    pub dedupe_lines: bool,
+    /// Stop at the first file or line which fails instead of skipping it.
+    pub strict: bool,
+    /// Replace invalid UTF-8 in plain text files with `U+FFFD` instead of skipping the rest of the file.
+    /// The number of replaced bytes is counted in `AnalysisStats::invalid_utf8_byte_count`.
+    pub lossy_utf8: bool,
+    /// Unicode normalization applied to plain text before it is tokenized. Not applied to Mokuro files.
+    pub normalization: TextNormalization,
+    /// Receives the words of each analyzed chunk of text, one per line, in the order they were found.
+    pub word_list_raw_writer: Option<Box<dyn Write + Send>>,
+    /// Counts the files, bytes, and lines analyzed so far. Display it with a `ProgressReporter`.
+    pub progress: Option<Arc<Progress>>,
+    /// Reuses the stats of files `analyze_files` analyzed on previous runs and stores the stats of new or changed files.
+    ///
+    /// Not used with `dedupe_lines` for `AnalysisType::MokuroJson` since the lines of a page then depend on the other pages of the volume.
+    pub stats_cache: Option<StatsCache>,
+}
+
+/// Tokenizes and analyzes Japanese text.
+///
+/// The dictionary is only borrowed so it can be loaded once with `dict_handler::make_sudachi_dict` and shared by several analyzers.
+/// An analyzer can be used from several threads at once.
+///
+/// Lines which fail to tokenize and files `analyze_files` fails to read are skipped and can be retrieved with `take_errors`.
+/// In strict mode they are returned as errors instead.
+///
+/// Words for the `word_list_raw_writer` are written from a background thread. Call `finish` to wait for them to be written and get any write error.
+///
+/// The words an analyzer finds stay interned after it is dropped, see `interner::Word`.
+pub struct Analyzer<'a> {
+    tokenizer: StatelessTokenizer<&'a JapaneseDictionary>,
+    analysis_type: AnalysisType,
+    singlethreaded: bool,
+    chunk_size: Option<usize>,
+    strict: bool,
+    lossy_utf8: bool,
+    normalization: TextNormalization,
+    noise_filter: NoiseFilter,
+    line_deduplicator: Option<LineDeduplicator>,
+    word_list_raw_writer: Option<WordListRawWriter>,
+    progress: Option<Arc<Progress>>,
+    stats_cache: Option<StatsCache>,
+    cache_hit_count: AtomicU64,
+    errors: Mutex<Vec<AnalyzerError>>,
+}
+
+impl<'a> Analyzer<'a> {
+    pub fn new(dictionary: &'a JapaneseDictionary, options: AnalyzerOptions) -> Self {
+        let stats_cache = match (options.analysis_type, options.dedupe_lines) {
+            (AnalysisType::MokuroJson, true) => None,
+            _ => options.stats_cache.map(|mut stats_cache| {
+                //Singlethreaded runs store their words in text order, so they don't share entries with parallel runs
+                stats_cache.settings_key = format!(
+                    "{:?}|{:?}|{}|{}|{}|{:?}|{}",
+                    dict_handler::get_mode(),
+                    options.analysis_type,
+                    options.noise_filter.get_cache_key(),
+                    options.dedupe_lines,
+                    options.lossy_utf8,
+                    options.normalization,
+                    options.singlethreaded
+                );
+                stats_cache
+            }),
+        };
+        return Analyzer {
+            tokenizer: StatelessTokenizer::new(dictionary),
+            analysis_type: options.analysis_type,
+            singlethreaded: options.singlethreaded,
+            chunk_size: options
+                .chunk_size
+                .map(|x| x.max(file_handler::MIN_PLAIN_BUFFER_SIZE)),
+            strict: options.strict,
+            lossy_utf8: options.lossy_utf8,
+            normalization: options.normalization,
+            noise_filter: options.noise_filter,
+            line_deduplicator: match options.dedupe_lines {
+                true => Some(LineDeduplicator::default()),
+                false => None,
+            },
+            word_list_raw_writer: options.word_list_raw_writer.map(WordListRawWriter::start),
+            progress: options.progress,
+            stats_cache: stats_cache,
+            cache_hit_count: Default::default(),
+            errors: Default::default(),
+        };
+    }
+
+    /// Analyzes plain text. `volume_path` is the name the text is counted under in `AnalysisStats::volume_lengths`.
+    pub fn analyze_str(
+        &self,
+        text: &str,
+        volume_path: &str,
+    ) -> Result<AnalysisStats, AnalyzerError> {
+        let text_source = TextSource {
+            volume_path: volume_path.to_string(),
+            is_page: false,
+        };
+        let text_chunk = TextChunk {
+            byte_count: text.len() as u64,
+            ..TextChunk::from(file_handler::split_plain_text(
+                &file_handler::normalize_text(text, self.normalization),
+            ))
+        };
+        let text_chunk = self.remove_duplicate_lines(text_chunk, &text_source);
+        return self.analyze_text_chunk(text_chunk, &text_source);
+    }
+
+    /// Analyzes plain text from a reader in chunks of about `chunk_size` bytes, or `DEFAULT_PLAIN_BUFFER_SIZE` if not set.
+    pub fn analyze_reader<R: BufRead>(
+        &self,
+        reader: R,
+        volume_path: &str,
+    ) -> Result<AnalysisStats, AnalyzerError> {
+        let chunk_size = self
+            .chunk_size
+            .unwrap_or(file_handler::DEFAULT_PLAIN_BUFFER_SIZE);
+        let mut stats = AnalysisStats::default();
+        let mut text = String::new();
+        for line in reader.lines() {
+            text += &line.map_err(|err| AnalyzerError::Io {
+                path: volume_path.to_string(),
+                source: err,
+            })?;
+            text += "\n";
+            if text.len() >= chunk_size {
+                stats = stats.combine(self.analyze_str(&text, volume_path)?);
+                text.clear();
+            }
+        }
+        if !text.is_empty() {
+            stats = stats.combine(self.analyze_str(&text, volume_path)?);
+        }
+        return Ok(stats);
+    }
+
+    /// Analyzes a single file read according to the `analysis_type` of the analyzer.
+    pub fn analyze_file(&self, filepath: &PathBuf) -> Result<AnalysisStats, AnalyzerError> {
+        let file_results = self.process_file(filepath, &self.get_text_source(filepath), None);
+        match file_results.error {
+            Some(some) => return Err(some),
+            None => return Ok(file_results.stats),
+        }
+    }
+
+    /// Analyzes several files and combines their stats by volume path. Files which fail to read are skipped unless the analyzer is strict.
+    ///
+    /// A plain text file which fails partway through keeps the stats of the part read before the error.
+    /// Files found in the `stats_cache` are not analyzed again.
+    pub fn analyze_files(
+        &self,
+        filepaths: &Vec<PathBuf>,
+    ) -> Result<HashMap<String, AnalysisStats>, AnalyzerError> {
+        if let Some(progress) = &self.progress {
+            let byte_count: u64 = match self.analysis_type {
+                AnalysisType::Any => filepaths
+                    .iter()
+                    .filter_map(|x| std::fs::metadata(x).ok())
+                    .map(|x| x.len())
+                    .sum(),
+                AnalysisType::MokuroJson | AnalysisType::Mokuro => 0,
+            };
+            progress.add_total(filepaths.len() as u64, byte_count);
+        }
+
+        //Each thread folds the files it analyzes into its own stats which are only combined at the end
+        let add_file = |mut volume_stats: HashMap<String, AnalysisStats>, filepath: &PathBuf| {
+            let text_source = self.get_text_source(filepath);
+            let file_results = self.analyze_file_with_cache(filepath, &text_source);
+            //Skipped files which weren't read at all don't become empty volumes
+            let skipped_file = file_results
+                .error
+                .as_ref()
+                .is_some_and(|x| x.is_skipped_input())
+                && file_results.stats.is_empty();
+            if !skipped_file {
+                volume_stats.merge_with(
+                    text_source.volume_path,
+                    file_results.stats,
+                    AnalysisStats::combine,
+                );
+            }
+            if let Some(progress) = &self.progress {
+                progress.add_file();
+            }
+            match file_results.error {
+                None => {}
+                Some(some) if some.is_skipped_input() => self.skip_input(vec![some])?,
+                Some(some) => return Err(some),
+            }
+            return Ok(volume_stats);
+        };
+
+        //Pages are deduplicated against the earlier pages of their volume so they can't be analyzed in any order
+        if self.line_deduplicator.is_some()
+            && matches!(self.analysis_type, AnalysisType::MokuroJson)
+        {
+            let add_volume = |volume_stats: HashMap<String, AnalysisStats>,
+                              volume_filepaths: &Vec<&PathBuf>| {
+                volume_filepaths
+                    .iter()
+                    .copied()
+                    .try_fold(volume_stats, &add_file)
+            };
+            let volumes = group_pages_by_volume(filepaths);
+            if self.singlethreaded {
+                return volumes.iter().try_fold(HashMap::new(), add_volume);
+            }
+            return volumes
+                .par_iter()
+                .try_fold(HashMap::new, add_volume)
+                .try_reduce(HashMap::new, |x, y| Ok(combine_volume_stats(x, y)));
+        }
+        if self.singlethreaded {
+            return filepaths.iter().try_fold(HashMap::new(), add_file);
+        }
+        return filepaths
+            .par_iter()
+            .try_fold(HashMap::new, add_file)
+            .try_reduce(HashMap::new, |x, y| Ok(combine_volume_stats(x, y)));
+    }
+
+    /// Number of files `analyze_files` took from the `stats_cache` instead of analyzing them.
+    pub fn get_cache_hit_count(&self) -> u64 {
+        return self.cache_hit_count.load(Ordering::Relaxed);
+    }
+
+    /// Tokenizes and analyzes lines which were already read. Lines are not deduplicated.
+    pub fn analyze_text_chunk(
+        &self,
+        text_chunk: TextChunk,
+        text_source: &TextSource,
+    ) -> Result<AnalysisStats, AnalyzerError> {
+        return Ok(self
+            .analyze_text_chunk_results(text_chunk, text_source, None)?
+            .stats);
+    }
+
+    /// Waits for all words to be written to the `word_list_raw_writer`. Dropping the analyzer also waits but ignores write errors.
+    pub fn finish(mut self) -> Result<(), AnalyzerError> {
+        match &mut self.word_list_raw_writer {
+            Some(some) => return some.finish(),
+            None => return Ok(()),
+        }
+    }
+
+    //Words are also written to the stats cache when the file will be stored in it
+    fn analyze_text_chunk_results(
+        &self,
+        text_chunk: TextChunk,
+        text_source: &TextSource,
+        word_list_raw_file: Option<&WordListRawCacheFile>,
+    ) -> Result<FileResults, AnalyzerError> {
+        let line_count = text_chunk.lines.len() as u64;
+        let byte_count = text_chunk.byte_count;
+        let ends_within_line = text_chunk.ends_within_line;
+        let (morpheme_surfaces, tokenize_errors) =
+            run_tokenization(&text_chunk.lines, &self.tokenizer, &text_source.volume_path);
+        let skipped_lines = !tokenize_errors.is_empty();
+        self.skip_input(tokenize_errors)?;
+        if let Some(word_list_raw_writer) = &self.word_list_raw_writer {
+            let word_list_raw = format_word_list_raw(
+                morpheme_surfaces
+                    .iter()
+                    .flatten()
+                    .map(|x| x.as_str())
+                    .filter(|x| !analyzer::check_if_blacklisted(x)),
+            );
+            if let Some(some) = word_list_raw_file {
+                some.write(&word_list_raw);
+            }
+            word_list_raw_writer.write(word_list_raw)?;
+        }
+        let new_stats = stats_handler::get_stats(text_chunk, morpheme_surfaces, text_source);
+        if let Some(progress) = &self.progress {
+            progress.add_lines(line_count);
+            progress.add_bytes(byte_count);
+        }
+        return Ok(FileResults {
+            stats: new_stats,
+            skipped_lines: skipped_lines,
+            split_lines: ends_within_line,
+            error: None,
+        });
+    }
+
+    fn analyze_file_with_cache(&self, filepath: &PathBuf, text_source: &TextSource) -> FileResults {
+        let Some(stats_cache) = &self.stats_cache else {
+            return self.process_file(filepath, text_source, None);
+        };
+        let file_key = match stats_cache.lookup(filepath) {
+            //Files with lines cut between chunks are analyzed again when the chunk size changed
+            CacheLookup::Hit(entry)
+                if entry
+                    .chunk_size
+                    .is_some_and(|x| x != self.get_chunk_size(filepath)) =>
+            {
+                entry.key
+            }
+            CacheLookup::Hit(entry) => match self.word_list_raw_writer.is_some() {
+                //Entries stored without their words can't be used when the words are needed
+                true => match stats_cache.open_word_list_raw(&entry) {
+                    Some(some) => {
+                        return self.use_cache_entry(filepath, text_source, entry, Some(some));
+                    }
+                    None => entry.key,
+                },
+                false => return self.use_cache_entry(filepath, text_source, entry, None),
+            },
+            CacheLookup::Miss(some) => some,
+            CacheLookup::Unavailable => return self.process_file(filepath, text_source, None),
+        };
+
+        //The file is still analyzed if its words can't be written to the cache but is then not stored
+        let word_list_raw_file = match self.word_list_raw_writer.is_some() {
+            true => match stats_cache.create_word_list_raw_file(&file_key) {
+                Ok(ok) => Some(ok),
+                Err(_) => return self.process_file(filepath, text_source, None),
+            },
+            false => None,
+        };
+        let mut file_results =
+            self.process_file(filepath, text_source, word_list_raw_file.as_ref());
+        //Files with skipped lines are analyzed again so the lines are reported again
+        if file_results.error.is_none() && !file_results.skipped_lines {
+            let chunk_size = match file_results.split_lines {
+                true => Some(self.get_chunk_size(filepath)),
+                false => None,
+            };
+            let mut entry = StatsCacheEntry::new(file_key, file_results.stats, chunk_size);
+            let _ = match word_list_raw_file {
+                Some(some) => stats_cache.store_with_word_list_raw(&mut entry, some),
+                None => stats_cache.store(&mut entry),
+            };
+            file_results.stats = entry.stats;
+        }
+        return file_results;
+    }
+
+    fn use_cache_entry(
+        &self,
+        filepath: &PathBuf,
+        text_source: &TextSource,
+        mut entry: StatsCacheEntry,
+        word_list_raw: Option<std::fs::File>,
+    ) -> FileResults {
+        self.cache_hit_count.fetch_add(1, Ordering::Relaxed);
+        //The file may have been found through a different start path than when it was cached
+        entry.stats.volume_lengths = HashMap::from([(
+            text_source.volume_path.clone(),
+            entry.stats.volume_lengths.values().sum(),
+        )]);
+        if let Some(progress) = &self.progress
+            && matches!(self.analysis_type, AnalysisType::Any)
+        {
+            progress.add_bytes(std::fs::metadata(filepath).map_or(0, |x| x.len()));
+        }
+        let write_result = match (&self.word_list_raw_writer, word_list_raw) {
+            (Some(word_list_raw_writer), Some(word_list_raw)) => {
+                word_list_raw_writer.copy_from(std::io::BufReader::new(word_list_raw))
+            }
+            _ => Ok(()),
+        };
+        return FileResults {
+            stats: entry.stats,
+            error: write_result.err(),
+            ..Default::default()
+        };
+    }
+
+    /// Takes the errors of everything skipped so far.
+    pub fn take_errors(&self) -> Vec<AnalyzerError> {
+        return std::mem::take(&mut *self.errors.lock().expect("Failed to get errors reader"));
+    }
+
+    //Strict analyzers return the first error instead of skipping, except for ignored input which is always only listed
+    fn skip_input(&self, errors: Vec<AnalyzerError>) -> Result<(), AnalyzerError> {
+        let (ignored, errors): (Vec<AnalyzerError>, Vec<AnalyzerError>) =
+            errors.into_iter().partition(|x| x.is_ignored_input());
+        let mut skipped = self.errors.lock().expect("Failed to get errors writer");
+        skipped.extend(ignored);
+        if self.strict {
+            if let Some(some) = errors.into_iter().next() {
+                return Err(some);
+            }
+            return Ok(());
+        }
+        skipped.extend(errors);
+        return Ok(());
+    }
+
+    pub fn get_text_source(&self, filepath: &PathBuf) -> TextSource {
+        return TextSource {
+            volume_path: file_handler::get_volume_path(filepath, &self.analysis_type),
+            is_page: matches!(
+                self.analysis_type,
+                AnalysisType::MokuroJson | AnalysisType::Mokuro
+            ),
+        };
+    }
+
+    //Stats of the part read before an error are kept so plain text files which fail partway through still count
+    fn process_file(
+        &self,
+        filepath: &PathBuf,
+        text_source: &TextSource,
+        word_list_raw_file: Option<&WordListRawCacheFile>,
+    ) -> FileResults {
+        let failed = AtomicBool::new(false);
+        match self.analysis_type {
+            AnalysisType::MokuroJson => {
+                let text_chunk = file_handler::get_json_file_data(
+                    filepath,
+                    &self.noise_filter,
+                    self.line_deduplicator.as_ref(),
+                );
+                return self.fold_text_chunks(
+                    std::iter::once(text_chunk),
+                    text_source,
+                    word_list_raw_file,
+                    &failed,
+                );
+            }
+            AnalysisType::Mokuro => {
+                let pages = match file_handler::get_mokuro_file_data(
+                    filepath,
+                    &self.noise_filter,
+                    self.line_deduplicator.as_ref(),
+                ) {
+                    Ok(some) => some,
+                    Err(err) => return FileResults::from(err),
+                };
+                if self.singlethreaded {
+                    return self.fold_text_chunks(
+                        pages.into_iter().map(Ok),
+                        text_source,
+                        word_list_raw_file,
+                        &failed,
+                    );
+                }
+                return self.fold_text_chunks_parallel(
+                    pages.into_par_iter().map(Ok),
+                    text_source,
+                    word_list_raw_file,
+                    &failed,
+                );
+            }
+            AnalysisType::Any => {
+                let text_chunks = match file_handler::BufferedPlainLineReader::with_chunk_size(
+                    filepath,
+                    self.get_chunk_size(filepath),
+                ) {
+                    Ok(mut some) => {
+                        some.read_lossy(self.lossy_utf8);
+                        some.normalize(self.normalization);
+                        some.map(|x| {
+                            x.map(|text_chunk| self.remove_duplicate_lines(text_chunk, text_source))
+                        })
+                    }
+                    Err(err) => return FileResults::from(err),
+                };
+                if self.singlethreaded {
+                    return self.fold_text_chunks(
+                        text_chunks,
+                        text_source,
+                        word_list_raw_file,
+                        &failed,
+                    );
+                }
+                return self.fold_text_chunks_parallel(
+                    text_chunks
+                        .take_while(|_| !failed.load(Ordering::Relaxed))
+                        .par_bridge(),
+                    text_source,
+                    word_list_raw_file,
+                    &failed,
+                );
+            }
+        };
+    }
+
+    fn fold_text_chunks<I: Iterator<Item = Result<TextChunk, AnalyzerError>>>(
+        &self,
+        text_chunks: I,
+        text_source: &TextSource,
+        word_list_raw_file: Option<&WordListRawCacheFile>,
+        failed: &AtomicBool,
+    ) -> FileResults {
+        return text_chunks
+            .take_while(|_| !failed.load(Ordering::Relaxed))
+            .fold(FileResults::default(), |file_results, text_chunk| {
+                self.add_text_chunk(
+                    file_results,
+                    text_chunk,
+                    text_source,
+                    word_list_raw_file,
+                    failed,
+                )
+            });
+    }
+
+    //Each thread folds the chunks it analyzes into its own results which are only combined at the end
+    fn fold_text_chunks_parallel<P: ParallelIterator<Item = Result<TextChunk, AnalyzerError>>>(
+        &self,
+        text_chunks: P,
+        text_source: &TextSource,
+        word_list_raw_file: Option<&WordListRawCacheFile>,
+        failed: &AtomicBool,
+    ) -> FileResults {
+        return text_chunks
+            .fold(FileResults::default, |file_results, text_chunk| {
+                self.add_text_chunk(
+                    file_results,
+                    text_chunk,
+                    text_source,
+                    word_list_raw_file,
+                    failed,
+                )
+            })
+            .reduce(FileResults::default, FileResults::combine);
+    }
+
+    fn add_text_chunk(
+        &self,
+        mut file_results: FileResults,
+        text_chunk: Result<TextChunk, AnalyzerError>,
+        text_source: &TextSource,
+        word_list_raw_file: Option<&WordListRawCacheFile>,
+        failed: &AtomicBool,
+    ) -> FileResults {
+        //Chunks already handed to other threads are skipped after an error
+        if failed.load(Ordering::Relaxed) {
+            return file_results;
+        }
+        match text_chunk
+            .and_then(|x| self.analyze_text_chunk_results(x, text_source, word_list_raw_file))
+        {
+            Ok(new_results) => {
+                file_results = file_results.combine(new_results);
+            }
+            Err(err) => {
+                failed.store(true, Ordering::Relaxed);
+                file_results.error = Some(err);
+            }
+        }
+        return file_results;
+    }
+
+    fn get_chunk_size(&self, filepath: &PathBuf) -> usize {
+        if let Some(some) = self.chunk_size {
+            return some;
+        }
+        if self.singlethreaded {
+            return file_handler::DEFAULT_PLAIN_BUFFER_SIZE;
+        }
+        let file_size = std::fs::metadata(filepath).map_or(0, |x| x.len());
+        return file_handler::get_adaptive_chunk_size(file_size, rayon::current_num_threads());
+    }
+
+    fn remove_duplicate_lines(&self, text_chunk: TextChunk, text_source: &TextSource) -> TextChunk {
+        match &self.line_deduplicator {
+            Some(some) => return some.remove_duplicates(&text_source.volume_path, text_chunk),
+            None => return text_chunk,
+        }
+    }
+}
+
+/// Stats of the chunks of a file analyzed so far.
+#[derive(Default)]
+struct FileResults {
+    stats: AnalysisStats,
+    skipped_lines: bool,
+    /// A chunk was cut inside a line.
+    split_lines: bool,
+    error: Option<AnalyzerError>,
+}
+
+impl FileResults {
+    fn combine(self, file_results2: FileResults) -> FileResults {
+        return FileResults {
+            stats: self.stats.combine(file_results2.stats),
+            skipped_lines: self.skipped_lines || file_results2.skipped_lines,
+            split_lines: self.split_lines || file_results2.split_lines,
+            error: self.error.or(file_results2.error),
+        };
+    }
+}
+
+impl From<AnalyzerError> for FileResults {
+    fn from(err: AnalyzerError) -> Self {
+        return FileResults {
+            error: Some(err),
+            ..Default::default()
+        };
+    }
+}
+
+/// Groups Mokuro page json files by volume with the pages of each volume in path order.
+fn group_pages_by_volume(filepaths: &Vec<PathBuf>) -> Vec<Vec<&PathBuf>> {
+    let mut volumes: BTreeMap<String, Vec<&PathBuf>> = Default::default();
+    for filepath in filepaths {
+        volumes
+            .entry(file_handler::get_volume_path(
+                filepath,
+                &AnalysisType::MokuroJson,
+            ))
+            .or_default()
+            .push(filepath);
+    }
+    return volumes
+        .into_values()
+        .map(|mut x| {
+            x.sort();
+            x
+        })
+        .collect();
+}
+
+fn combine_volume_stats(
+    volume_stats1: HashMap<String, AnalysisStats>,
+    volume_stats2: HashMap<String, AnalysisStats>,
+) -> HashMap<String, AnalysisStats> {
+    let (mut larger, smaller) = match volume_stats1.len() >= volume_stats2.len() {
+        true => (volume_stats1, volume_stats2),
+        false => (volume_stats2, volume_stats1),
+    };
+    for (volume_path, stats) in smaller {
+        larger.merge_with(volume_path, stats, AnalysisStats::combine);
+    }
+    return larger;
+}
+
+/// Tokenizes each line into its interned morpheme surfaces. Lines which fail to tokenize have no morphemes and an error.
+///
+/// `path` is only used for the errors.
+pub fn run_tokenization(
+    lines: &Vec<String>,
+    tokenizer: &StatelessTokenizer<&JapaneseDictionary>,
+    path: &str,
+) -> (Vec<Vec<Word>>, Vec<AnalyzerError>) {
+    let mut line_morpheme_surfaces: Vec<Vec<Word>> = Default::default();
+    let mut errors: Vec<AnalyzerError> = Default::default();
+    for line in lines {
+        let mut morpheme_surfaces: Vec<Word> = Default::default();
+        match sudachi::analysis::Tokenize::tokenize(
+            tokenizer,
+            line,
+            dict_handler::get_mode(),
+            false,
+        ) {
+            Ok(morphemes) => {
+                for morpheme in morphemes.iter() {
+                    morpheme_surfaces.push(Word::new(&morpheme.surface()));
+                }
+            }
+            Err(err) => {
+                errors.push(AnalyzerError::Tokenize {
+                    path: path.to_string(),
+                    line: line.to_string(),
+                    message: err.to_string(),
+                });
+            }
+        };
+        line_morpheme_surfaces.push(morpheme_surfaces);
+    }
+    return (line_morpheme_surfaces, errors);
+}
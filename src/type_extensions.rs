@@ -1,29 +1,16 @@
-use std::{
-    mem,
-    sync::{Mutex, PoisonError},
-};
+use std::{collections::HashMap, hash::Hash};
 
-pub trait MutexExtensions<T, F> {
-    fn replace_with(&self, value: F) -> Result<(), PoisonError<F>>;
+pub trait HashMapExtensions<K, V> {
+    fn merge_with<F: FnOnce(V, V) -> V>(&mut self, key: K, value: V, f: F);
 }
 
-impl<T: std::default::Default, F: FnOnce(T) -> T> MutexExtensions<T, F> for Mutex<T> {
-    /// Calls `f` and replaces the contained value with the result.
-    ///
-    /// # Errors
-    ///
-    /// If another user of this mutex panicked while holding the mutex, then
-    /// this call will return an error containing the provided `value` instead.
-    ///
-    /// Partially copied from https://doc.rust-lang.org/std/sync/struct.Mutex.html#method.replace (nightly-only)
-    fn replace_with(&self, f: F) -> Result<(), PoisonError<F>> {
-        match self.lock() {
-            Ok(mut guard) => {
-                let mutex_inner = mem::replace(&mut *guard, T::default());
-                let _ = mem::replace(&mut *guard, f(mutex_inner));
-                Ok(())
-            }
-            Err(_) => Err(PoisonError::new(f)),
-        }
+impl<K: Eq + Hash, V> HashMapExtensions<K, V> for HashMap<K, V> {
+    /// Inserts `value` at `key`. If `key` already has a value, it is replaced with the result of calling `f` on the existing value and `value`.
+    fn merge_with<F: FnOnce(V, V) -> V>(&mut self, key: K, value: V, f: F) {
+        let merged_value = match self.remove(&key) {
+            Some(some) => f(some, value),
+            None => value,
+        };
+        self.insert(key, merged_value);
     }
 }
@@ -1,19 +1,34 @@
 use std::{
+    borrow::Cow,
     fs::File,
     io::{Read, Seek},
     path::PathBuf,
 };
 
+/// Text decoded from part of a file.
+pub struct DecodedText<'a> {
+    pub text: Cow<'a, str>,
+    /// Number of bytes of the file the text was decoded from.
+    pub byte_count: u64,
+    /// Number of invalid bytes replaced with `U+FFFD`. Only above 0 when reading lossy.
+    pub replaced_byte_count: u64,
+    /// Whether the text was cut inside a line instead of at a line break or the end of the file.
+    pub ends_within_line: bool,
+}
+
 /// Safely reads a UTF8 file buffered without chopping multi-byte characters in half.
 ///
-/// Iterator returns `None` upon finding invalid UTF8 (enable `read_unsafe` to override this) and once the file has been completed.
+/// Iterator returns an `InvalidData` error upon finding invalid UTF8 (enable `read_lossy` to replace it instead) and `None` once the file has been completed.
 ///
-/// Iterator `String` chunks will always be less than or equal to the provided `buffer_size`.
+/// Iterator `DecodedText` chunks will always be read from less than or equal to the provided `buffer_size` bytes.
 pub struct Utf8BufReader {
     file: File,
     buffer: Vec<u8>,
+    buffer_size: usize,
+    //byte offset in the file of the start of the next chunk
+    position: u64,
     end_of_file: bool,
-    read_unsafe: bool,
+    read_lossy: bool,
 }
 
 impl Utf8BufReader {
@@ -22,56 +37,128 @@ impl Utf8BufReader {
         Ok(Self {
             file: file,
             buffer: vec![0u8; buffer_size],
+            buffer_size: buffer_size,
+            position: 0,
             end_of_file: false,
-            read_unsafe: false,
+            read_lossy: false,
         })
     }
 
-    #[allow(dead_code)]
-    pub fn read_unsafe(&mut self, read_unsafe: bool) {
-        self.read_unsafe = read_unsafe;
+    /// Replace each invalid UTF8 sequence with `U+FFFD` and keep reading instead of stopping with an error.
+    pub fn read_lossy(&mut self, read_lossy: bool) {
+        self.read_lossy = read_lossy;
     }
 }
 
 impl Iterator for Utf8BufReader {
-    type Item = String;
+    type Item = Result<DecodedText<'static>, std::io::Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.end_of_file {
             return None;
         }
 
-        let buffer_length = self.buffer.len();
-        let bytes_filled = self.file.read(&mut self.buffer).ok()?;
-        self.end_of_file = bytes_filled < buffer_length;
-        self.buffer.resize(bytes_filled, b'\0');
-        let (file_contents, seek_position) = parse_utf8_buffer(&self.buffer, self.read_unsafe);
-        if file_contents.len() == 0 {
-            return None;
+        self.buffer.resize(self.buffer_size, b'\0');
+        let bytes_filled = match self.file.read(&mut self.buffer) {
+            Ok(ok) => ok,
+            Err(err) => {
+                self.end_of_file = true;
+                return Some(Err(err));
+            }
+        };
+        self.buffer.truncate(bytes_filled);
+        let decoded_text = match self.read_lossy {
+            true => decode_utf8_lossy(&self.buffer, bytes_filled < self.buffer_size),
+            false => decode_utf8(&self.buffer),
+        };
+        let seek_position = decoded_text.byte_count as usize;
+        if seek_position == 0 {
+            self.end_of_file = true;
+            if bytes_filled == 0 {
+                return None;
+            }
+            return Some(Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid UTF-8 at byte {}", self.position),
+            )));
+        }
+        //a chunk cut short by invalid UTF8 is not the end of the file even if the read was
+        self.end_of_file = bytes_filled < self.buffer_size && seek_position == bytes_filled;
+        if seek_position < bytes_filled {
+            if let Err(err) = self.file.seek(std::io::SeekFrom::Current(
+                seek_position as i64 - bytes_filled as i64,
+            )) {
+                self.end_of_file = true;
+                return Some(Err(err));
+            }
         }
-        let _ = self.file.seek(std::io::SeekFrom::Current(
-            seek_position as i64 - buffer_length as i64,
-        ));
-        return Some(file_contents);
+        self.position += seek_position as u64;
+        //Buffers are filled without looking for line breaks
+        return Some(Ok(DecodedText {
+            text: Cow::Owned(decoded_text.text.into_owned()),
+            ends_within_line: !self.end_of_file,
+            ..decoded_text
+        }));
     }
 }
 
-fn parse_utf8_buffer(file_buffer: &Vec<u8>, read_unsafe: bool) -> (String, usize) {
-    let mut seek_position: usize = file_buffer.len();
-    let utf8_string = match std::str::from_utf8(file_buffer) {
-        Ok(ok) => ok.to_string(),
-        Err(err) => {
-            seek_position = err.valid_up_to();
-            if seek_position == 0 && read_unsafe {
-                seek_position = file_buffer.len();
-                unsafe { std::str::from_utf8_unchecked(&file_buffer) }.to_string()
-            } else {
-                //should never panic as long as seek_position = err.valid_up_to()
-                std::str::from_utf8(&file_buffer[0..seek_position])
-                    .unwrap()
-                    .to_string()
+/// Decodes the valid UTF8 at the start of `bytes`.
+pub fn decode_utf8(bytes: &[u8]) -> DecodedText<'_> {
+    let valid_length = match std::str::from_utf8(bytes) {
+        Ok(_) => bytes.len(),
+        Err(err) => err.valid_up_to(),
+    };
+    return DecodedText {
+        //should never fail as long as valid_length = err.valid_up_to()
+        text: Cow::Borrowed(std::str::from_utf8(&bytes[..valid_length]).unwrap()),
+        byte_count: valid_length as u64,
+        replaced_byte_count: 0,
+        ends_within_line: false,
+    };
+}
+
+/// Decodes UTF8, replacing each invalid sequence with `U+FFFD`.
+///
+/// A character cut off at the end of `bytes` is left undecoded for the next read unless `at_end` is set.
+pub fn decode_utf8_lossy(bytes: &[u8], at_end: bool) -> DecodedText<'_> {
+    let mut text = String::new();
+    let mut position = 0;
+    let mut replaced_byte_count = 0;
+    while position < bytes.len() {
+        let err = match std::str::from_utf8(&bytes[position..]) {
+            Ok(ok) if position == 0 => {
+                return DecodedText {
+                    text: Cow::Borrowed(ok),
+                    byte_count: bytes.len() as u64,
+                    replaced_byte_count: 0,
+                    ends_within_line: false,
+                };
             }
-        }
+            Ok(ok) => {
+                text.push_str(ok);
+                position = bytes.len();
+                break;
+            }
+            Err(err) => err,
+        };
+        let valid_end = position + err.valid_up_to();
+        text.push_str(std::str::from_utf8(&bytes[position..valid_end]).unwrap());
+        let invalid_length = match err.error_len() {
+            Some(some) => some,
+            None if at_end => bytes.len() - valid_end,
+            None => {
+                position = valid_end;
+                break;
+            }
+        };
+        text.push(char::REPLACEMENT_CHARACTER);
+        replaced_byte_count += invalid_length as u64;
+        position = valid_end + invalid_length;
+    }
+    return DecodedText {
+        text: Cow::Owned(text),
+        byte_count: position as u64,
+        replaced_byte_count: replaced_byte_count,
+        ends_within_line: false,
     };
-    (utf8_string, seek_position)
 }
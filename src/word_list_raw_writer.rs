@@ -0,0 +1,129 @@
+use std::{
+    io::{BufRead, BufWriter, Write},
+    sync::{
+        Arc, Mutex,
+        mpsc::{Receiver, SyncSender},
+    },
+    thread::JoinHandle,
+};
+
+use crate::error::AnalyzerError;
+
+//Chunks waiting to be written. Bounds memory use when the writer can't keep up
+const CHANNEL_CAPACITY: usize = 64;
+//Size of the chunks words read by `copy_from` are queued in
+const COPY_CHUNK_SIZE: usize = 1 << 20;
+
+/// Writes the words of analyzed chunks from a background thread so analyzer threads only have to hand them off instead of waiting on each other to write.
+pub struct WordListRawWriter {
+    sender: Option<SyncSender<Vec<u8>>>,
+    thread: Option<JoinHandle<()>>,
+    error: Arc<Mutex<Option<std::io::Error>>>,
+}
+
+impl WordListRawWriter {
+    pub fn start(writer: Box<dyn Write + Send>) -> Self {
+        let (sender, receiver) = std::sync::mpsc::sync_channel(CHANNEL_CAPACITY);
+        let error: Arc<Mutex<Option<std::io::Error>>> = Default::default();
+        let thread_error = error.clone();
+        let thread = std::thread::spawn(move || {
+            if let Err(err) = write_words(writer, receiver) {
+                *thread_error.lock().expect("Failed to get error writer") = Some(err);
+            }
+        });
+        return WordListRawWriter {
+            sender: Some(sender),
+            thread: Some(thread),
+            error: error,
+        };
+    }
+
+    /// Queues words formatted by `format_word_list_raw`.
+    pub fn write(&self, word_list_raw: String) -> Result<(), AnalyzerError> {
+        if word_list_raw.is_empty() {
+            return Ok(());
+        }
+        return self.send(word_list_raw.into_bytes());
+    }
+
+    /// Queues words which were already formatted by `format_word_list_raw`, such as ones stored in the stats cache, in chunks of whole lines.
+    pub fn copy_from<R: BufRead>(&self, mut reader: R) -> Result<(), AnalyzerError> {
+        loop {
+            let mut bytes = Vec::with_capacity(COPY_CHUNK_SIZE);
+            while bytes.len() < COPY_CHUNK_SIZE {
+                match reader.read_until(b'\n', &mut bytes) {
+                    Ok(0) => break,
+                    Ok(_) => {}
+                    Err(err) => return Err(AnalyzerError::WordListRaw(err)),
+                }
+            }
+            if bytes.is_empty() {
+                return Ok(());
+            }
+            self.send(bytes)?;
+        }
+    }
+
+    /// Writes everything still queued and returns the first error the writer stopped at.
+    pub fn finish(&mut self) -> Result<(), AnalyzerError> {
+        self.sender = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        match self
+            .error
+            .lock()
+            .expect("Failed to get error reader")
+            .take()
+        {
+            Some(some) => return Err(AnalyzerError::WordListRaw(some)),
+            None => return Ok(()),
+        }
+    }
+
+    fn send(&self, bytes: Vec<u8>) -> Result<(), AnalyzerError> {
+        let Some(sender) = &self.sender else {
+            return Err(self.take_error());
+        };
+        return sender.send(bytes).map_err(|_| self.take_error());
+    }
+
+    //The writer thread only stops early after an error
+    fn take_error(&self) -> AnalyzerError {
+        let error = self
+            .error
+            .lock()
+            .expect("Failed to get error reader")
+            .take();
+        return AnalyzerError::WordListRaw(
+            error.unwrap_or_else(|| std::io::Error::other("Stopped after an earlier error")),
+        );
+    }
+}
+
+/// Formats words one per line as they are written to the raw word list.
+pub fn format_word_list_raw<'w, I: Iterator<Item = &'w str>>(words: I) -> String {
+    let mut word_list_raw = String::new();
+    for word in words {
+        word_list_raw.push_str(word);
+        word_list_raw.push('\n');
+    }
+    return word_list_raw;
+}
+
+impl Drop for WordListRawWriter {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+fn write_words(
+    writer: Box<dyn Write + Send>,
+    receiver: Receiver<Vec<u8>>,
+) -> Result<(), std::io::Error> {
+    let mut writer = BufWriter::new(writer);
+    for bytes in receiver {
+        writer.write_all(&bytes)?;
+    }
+    return writer.flush();
+}